@@ -0,0 +1,95 @@
+use std::borrow::Cow;
+use std::io;
+use std::path::PathBuf;
+
+// Variable de entorno con el directorio de recursos para mods
+const OVERRIDE_ENV: &str = "TRAPPED_ASSETS_DIR";
+
+// Directorio de mods junto al ejecutable, usado si no se define la variable
+const OVERRIDE_DIR: &str = "mods";
+
+// Recursos incluidos en el binario, indexados por su ruta relativa al repositorio
+static EMBEDDED: &[(&str, &[u8])] = &[
+    ("textures/Cell.png", include_bytes!("../textures/Cell.png")),
+    ("textures/Controls.png", include_bytes!("../textures/Controls.png")),
+    ("textures/Door.jpeg", include_bytes!("../textures/Door.jpeg")),
+    ("textures/Ganar.png", include_bytes!("../textures/Ganar.png")),
+    ("textures/guard_sheet.png", include_bytes!("../textures/guard_sheet.png")),
+    ("textures/Inicio.png", include_bytes!("../textures/Inicio.png")),
+    ("textures/Perdida.png", include_bytes!("../textures/Perdida.png")),
+    ("textures/Police.png", include_bytes!("../textures/Police.png")),
+    ("textures/prison1.jpeg", include_bytes!("../textures/prison1.jpeg")),
+    ("textures/prison2.jpg", include_bytes!("../textures/prison2.jpg")),
+    ("textures/prison3.jpg", include_bytes!("../textures/prison3.jpg")),
+    ("textures/prison_wall.png", include_bytes!("../textures/prison_wall.png")),
+    ("Audio/Confirm.wav", include_bytes!("../Audio/Confirm.wav")),
+    ("Audio/Footsteps.wav", include_bytes!("../Audio/Footsteps.wav")),
+    ("Audio/Footsteps_concrete2.wav", include_bytes!("../Audio/Footsteps_concrete2.wav")),
+    ("Audio/Footsteps_gravel.wav", include_bytes!("../Audio/Footsteps_gravel.wav")),
+    ("Audio/Footsteps_gravel2.wav", include_bytes!("../Audio/Footsteps_gravel2.wav")),
+    ("Audio/Footsteps_metal.wav", include_bytes!("../Audio/Footsteps_metal.wav")),
+    ("Audio/Footsteps_metal2.wav", include_bytes!("../Audio/Footsteps_metal2.wav")),
+    ("Audio/Footsteps_water.wav", include_bytes!("../Audio/Footsteps_water.wav")),
+    ("Audio/Footsteps_water2.wav", include_bytes!("../Audio/Footsteps_water2.wav")),
+    ("Audio/Radio.wav", include_bytes!("../Audio/Radio.wav")),
+    ("Audio/Select.wav", include_bytes!("../Audio/Select.wav")),
+    ("Audio/Shot.wav", include_bytes!("../Audio/Shot.wav")),
+    ("Audio/hey.mp3", include_bytes!("../Audio/hey.mp3")),
+    ("fonts/mai10.ttf", include_bytes!("../fonts/mai10.ttf")),
+    ("fonts/Meditative.ttf", include_bytes!("../fonts/Meditative.ttf")),
+    ("src/maze1.txt", include_bytes!("maze1.txt")),
+    ("src/maze2.txt", include_bytes!("maze2.txt")),
+    ("src/maze3.txt", include_bytes!("maze3.txt")),
+];
+
+// Paquete de recursos: los archivos del directorio de mods tienen prioridad
+// sobre los incluidos en el binario, así el juego funciona desde cualquier
+// directorio de trabajo.
+pub struct AssetPack {
+    override_dir: Option<PathBuf>,
+}
+
+impl AssetPack {
+    pub fn new(override_dir: Option<PathBuf>) -> Self {
+        AssetPack { override_dir }
+    }
+
+    // Usa `TRAPPED_ASSETS_DIR` si está definida o la carpeta `mods` junto al
+    // ejecutable si existe
+    pub fn from_env() -> Self {
+        let override_dir = std::env::var_os(OVERRIDE_ENV)
+            .map(PathBuf::from)
+            .or_else(|| {
+                let exe = std::env::current_exe().ok()?;
+                let dir = exe.parent()?.join(OVERRIDE_DIR);
+                if dir.is_dir() { Some(dir) } else { None }
+            });
+
+        AssetPack::new(override_dir)
+    }
+
+    // Indica si el recurso está en el directorio de mods o en el binario
+    pub fn contains(&self, path: &str) -> bool {
+        if let Some(dir) = &self.override_dir {
+            if dir.join(path).is_file() {
+                return true;
+            }
+        }
+        EMBEDDED.iter().any(|(name, _)| *name == path)
+    }
+
+    pub fn read(&self, path: &str) -> io::Result<Cow<'static, [u8]>> {
+        if let Some(dir) = &self.override_dir {
+            let file = dir.join(path);
+            if file.is_file() {
+                return std::fs::read(file).map(Cow::Owned);
+            }
+        }
+
+        EMBEDDED
+            .iter()
+            .find(|(name, _)| *name == path)
+            .map(|(_, data)| Cow::Borrowed(*data))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not in the asset pack or override directory"))
+    }
+}
@@ -0,0 +1,310 @@
+use crate::texture::Texture;
+use crate::color::Color;
+use crate::asset_pack::AssetPack;
+use crate::fileReader::{parse_floor, parse_maze};
+use crate::glyph_cache::CachedFont;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView};
+use rodio::{Decoder, Source};
+use rusttype::Font;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Cursor;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Error al cargar un recurso, con la ruta que lo produjo
+#[derive(Debug)]
+pub enum AssetError {
+    Io { path: String, source: std::io::Error },
+    Image { path: String, source: image::ImageError },
+    Font { path: String },
+    Audio { path: String, source: rodio::decoder::DecoderError },
+    Maze { path: String },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::Io { path, source } => write!(f, "could not read asset '{}': {}", path, source),
+            AssetError::Image { path, source } => write!(f, "could not decode image '{}': {}", path, source),
+            AssetError::Font { path } => write!(f, "could not parse font '{}'", path),
+            AssetError::Audio { path, source } => write!(f, "could not decode audio '{}': {}", path, source),
+            AssetError::Maze { path } => write!(f, "maze '{}' is empty or not valid UTF-8", path),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+// Audio ya decodificado en memoria. Las muestras se comparten entre todas las
+// reproducciones, así que reproducir un sonido no vuelve a leer el archivo.
+pub struct Sound {
+    name: String,
+    channels: u16,
+    sample_rate: u32,
+    samples: Arc<Vec<i16>>,
+}
+
+impl Sound {
+    // Sonido vacío que sustituye a un audio que no se pudo cargar
+    pub fn silence(name: &str) -> Self {
+        Sound {
+            name: name.to_string(),
+            channels: 1,
+            sample_rate: 44100,
+            samples: Arc::new(Vec::new()),
+        }
+    }
+
+    // Ruta con la que se cargó el sonido
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Copia que se reproduce a otra velocidad (y por tanto otro tono); comparte
+    // las muestras con el original
+    pub fn with_speed(&self, speed: f32) -> Sound {
+        Sound {
+            name: self.name.clone(),
+            channels: self.channels,
+            sample_rate: ((self.sample_rate as f32 * speed) as u32).max(1),
+            samples: self.samples.clone(),
+        }
+    }
+
+    pub fn source(&self) -> SoundSource {
+        SoundSource {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            samples: self.samples.clone(),
+            position: 0,
+        }
+    }
+}
+
+pub struct SoundSource {
+    channels: u16,
+    sample_rate: u32,
+    samples: Arc<Vec<i16>>,
+    position: usize,
+}
+
+impl Iterator for SoundSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl Source for SoundSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.samples.len().saturating_sub(self.position))
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.samples.len() as u64 / self.channels.max(1) as u64;
+        Some(Duration::from_micros(frames * 1_000_000 / self.sample_rate.max(1) as u64))
+    }
+}
+
+// Recurso que se puede precargar antes de entrar a un nivel
+pub enum AssetRequest<'a> {
+    Texture(&'a str),
+    Font(&'a str),
+    Sound(&'a str),
+}
+
+// Textura de cuadros magenta y negro que sustituye a una imagen ausente
+fn checkerboard(width: usize, height: usize) -> Texture {
+    let magenta = Color::new(255, 0, 255);
+    let black = Color::new(0, 0, 0);
+    let square = (width.min(height) / 8).max(1);
+
+    let mut data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            data.push(if (x / square + y / square).is_multiple_of(2) { magenta } else { black });
+        }
+    }
+    Texture::new(width, height, data)
+}
+
+// Caché central de recursos indexada por ruta. Cada recurso se carga y se
+// decodifica una sola vez. Las texturas y los sonidos que faltan se sustituyen
+// por un tablero de cuadros o por silencio y se avisa por la salida de error.
+pub struct Assets {
+    pack: AssetPack,
+    textures: HashMap<String, Rc<Texture>>,
+    originals: HashMap<String, Rc<DynamicImage>>,
+    images: HashMap<(String, usize, usize), Rc<Texture>>,
+    fonts: HashMap<String, Rc<CachedFont>>,
+    sounds: HashMap<String, Rc<Sound>>,
+}
+
+impl Assets {
+    pub fn new(pack: AssetPack) -> Self {
+        Assets {
+            pack,
+            textures: HashMap::new(),
+            originals: HashMap::new(),
+            images: HashMap::new(),
+            fonts: HashMap::new(),
+            sounds: HashMap::new(),
+        }
+    }
+
+    fn read(&self, path: &str) -> Result<Cow<'static, [u8]>, AssetError> {
+        self.pack.read(path).map_err(|source| AssetError::Io { path: path.to_string(), source })
+    }
+
+    // Recursos opcionales: se comprueba antes de cargar para no avisar de que faltan
+    pub fn exists(&self, path: &str) -> bool {
+        self.pack.contains(path)
+    }
+
+    fn original(&mut self, path: &str) -> Result<Rc<DynamicImage>, AssetError> {
+        if let Some(img) = self.originals.get(path) {
+            return Ok(img.clone());
+        }
+
+        let data = self.read(path)?;
+        let img = Rc::new(image::load_from_memory(&data).map_err(|source| AssetError::Image { path: path.to_string(), source })?);
+
+        self.originals.insert(path.to_string(), img.clone());
+        Ok(img)
+    }
+
+    // Textura a su tamaño original (paredes, sprites)
+    pub fn texture(&mut self, path: &str) -> Rc<Texture> {
+        if let Some(texture) = self.textures.get(path) {
+            return texture.clone();
+        }
+
+        let texture = match self.original(path) {
+            Ok(img) => Rc::new(Texture::from_image(&img)),
+            Err(e) => {
+                eprintln!("warning: {}; using a placeholder texture", e);
+                Rc::new(checkerboard(64, 64))
+            }
+        };
+        self.textures.insert(path.to_string(), texture.clone());
+        texture
+    }
+
+    // Imagen redimensionada exactamente a `width` x `height`
+    pub fn scaled_image(&mut self, path: &str, width: usize, height: usize) -> Rc<Texture> {
+        let key = (path.to_string(), width, height);
+        if let Some(image) = self.images.get(&key) {
+            return image.clone();
+        }
+
+        let image = match self.original(path) {
+            Ok(img) => Rc::new(Texture::from_image(&img.resize_exact(width as u32, height as u32, FilterType::Lanczos3))),
+            Err(e) => {
+                eprintln!("warning: {}; using a placeholder image", e);
+                Rc::new(checkerboard(width, height))
+            }
+        };
+        self.images.insert(key, image.clone());
+        image
+    }
+
+    // Imagen ajustada a `width` manteniendo la relación de aspecto
+    pub fn width_fitted_image(&mut self, path: &str, width: usize) -> Rc<Texture> {
+        let height = match self.original(path) {
+            Ok(img) => {
+                let (img_width, img_height) = img.dimensions();
+                (img_height as f32 * (width as f32 / img_width.max(1) as f32)) as usize
+            }
+            Err(_) => width * 3 / 4,
+        };
+        self.scaled_image(path, width, height)
+    }
+
+    pub fn font(&mut self, path: &str) -> Result<Rc<CachedFont>, AssetError> {
+        if let Some(font) = self.fonts.get(path) {
+            return Ok(font.clone());
+        }
+
+        let data = self.read(path)?;
+        let font = match data {
+            Cow::Borrowed(bytes) => Font::try_from_bytes(bytes),
+            Cow::Owned(bytes) => Font::try_from_vec(bytes),
+        };
+        let font = Rc::new(CachedFont::new(font.ok_or_else(|| AssetError::Font { path: path.to_string() })?));
+        self.fonts.insert(path.to_string(), font.clone());
+        Ok(font)
+    }
+
+    fn load_sound(&self, path: &str) -> Result<Sound, AssetError> {
+        let decoder = Decoder::new(Cursor::new(self.read(path)?))
+            .map_err(|source| AssetError::Audio { path: path.to_string(), source })?;
+        Ok(Sound {
+            name: path.to_string(),
+            channels: decoder.channels(),
+            sample_rate: decoder.sample_rate(),
+            samples: Arc::new(decoder.collect()),
+        })
+    }
+
+    pub fn sound(&mut self, path: &str) -> Rc<Sound> {
+        if let Some(sound) = self.sounds.get(path) {
+            return sound.clone();
+        }
+
+        let sound = Rc::new(self.load_sound(path).unwrap_or_else(|e| {
+            eprintln!("warning: {}; playing silence instead", e);
+            Sound::silence(path)
+        }));
+        self.sounds.insert(path.to_string(), sound.clone());
+        sound
+    }
+
+    // Laberinto de un nivel tal como está en el archivo, con las marcas de aparición
+    pub fn maze(&self, path: &str) -> Result<Vec<Vec<char>>, AssetError> {
+        let data = self.read(path)?;
+        let text = std::str::from_utf8(&data).map_err(|_| AssetError::Maze { path: path.to_string() })?;
+        let maze = parse_maze(text);
+        if maze.is_empty() || maze[0].is_empty() {
+            return Err(AssetError::Maze { path: path.to_string() });
+        }
+        Ok(maze)
+    }
+
+    // Tipos de suelo del nivel (sección `[floor]` del archivo)
+    pub fn floor(&self, path: &str) -> Result<Vec<Vec<char>>, AssetError> {
+        let data = self.read(path)?;
+        let text = std::str::from_utf8(&data).map_err(|_| AssetError::Maze { path: path.to_string() })?;
+        Ok(parse_floor(text))
+    }
+
+    // Carga una lista de recursos llamando a `progress` después de cada uno
+    // con el número de recursos cargados, para poder dibujar una pantalla de carga
+    pub fn preload<F: FnMut(usize, usize)>(&mut self, requests: &[AssetRequest], mut progress: F) -> Result<(), AssetError> {
+        for (i, request) in requests.iter().enumerate() {
+            match request {
+                AssetRequest::Texture(path) => { self.texture(path); },
+                AssetRequest::Font(path) => { self.font(path)?; },
+                AssetRequest::Sound(path) => { self.sound(path); },
+            }
+            progress(i + 1, requests.len());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use crate::assets::Sound;
+use crate::audio_engine::{Bus, Emitter};
+
+use rodio::source::ChannelVolume;
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Frecuencia de corte del filtro paso bajo para sonidos detrás de paredes
+const MUFFLE_CUTOFF_HZ: u32 = 700;
+
+// Identificador de una pista de música abierta en el backend
+pub type MusicId = usize;
+
+// Salida de audio del motor. `AudioEngine` decide qué suena y con qué volumen;
+// el backend solo lo reproduce (o lo registra).
+pub trait AudioBackend {
+    // Efecto de una sola vez; `volume` ya incluye el volumen del bus
+    fn play(&mut self, sound: &Sound, bus: Bus, volume: f32, emitter: Option<&Emitter>);
+    // Abre una pista en bucle, inicialmente en silencio
+    fn start_music(&mut self, sound: &Sound) -> MusicId;
+    fn set_music_volume(&mut self, id: MusicId, volume: f32);
+    fn stop_music(&mut self, id: MusicId);
+    // Libera los efectos que ya terminaron
+    fn update(&mut self) {}
+}
+
+// Backend elegido al iniciar
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AudioBackendKind {
+    Auto,
+    Rodio,
+    Null,
+}
+
+impl AudioBackendKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            AudioBackendKind::Auto => "auto",
+            AudioBackendKind::Rodio => "rodio",
+            AudioBackendKind::Null => "null",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(AudioBackendKind::Auto),
+            "rodio" => Some(AudioBackendKind::Rodio),
+            "null" | "none" | "off" => Some(AudioBackendKind::Null),
+            _ => None,
+        }
+    }
+
+    // Crea el backend; en modo automático usa el nulo si no hay dispositivo
+    pub fn create(self) -> Box<dyn AudioBackend> {
+        match self {
+            AudioBackendKind::Null => Box::new(NullBackend::new()),
+            AudioBackendKind::Rodio | AudioBackendKind::Auto => match RodioBackend::new() {
+                Some(backend) => Box::new(backend),
+                None => {
+                    eprintln!("warning: no audio output device available; audio is disabled");
+                    Box::new(NullBackend::new())
+                }
+            },
+        }
+    }
+}
+
+pub struct RodioBackend {
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    voices: Vec<Sink>,
+    music: HashMap<MusicId, Sink>,
+    next_music_id: MusicId,
+}
+
+impl RodioBackend {
+    pub fn new() -> Option<Self> {
+        let (stream, handle) = OutputStream::try_default().ok()?;
+
+        Some(RodioBackend {
+            _stream: stream,
+            handle,
+            voices: Vec::new(),
+            music: HashMap::new(),
+            next_music_id: 0,
+        })
+    }
+}
+
+impl AudioBackend for RodioBackend {
+    fn play(&mut self, sound: &Sound, _bus: Bus, volume: f32, emitter: Option<&Emitter>) {
+        let sink = match Sink::try_new(&self.handle) {
+            Ok(sink) => sink,
+            Err(_) => return,
+        };
+        sink.set_volume(volume);
+
+        match emitter {
+            Some(emitter) => {
+                // Panorama de potencia constante
+                let angle = (emitter.pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+                let panned = ChannelVolume::new(sound.source(), vec![angle.cos(), angle.sin()]).convert_samples::<f32>();
+                if emitter.muffled {
+                    sink.append(panned.low_pass(MUFFLE_CUTOFF_HZ));
+                } else {
+                    sink.append(panned);
+                }
+            }
+            None => sink.append(sound.source()),
+        }
+
+        self.voices.push(sink);
+    }
+
+    fn start_music(&mut self, sound: &Sound) -> MusicId {
+        let id = self.next_music_id;
+        self.next_music_id += 1;
+
+        if let Ok(sink) = Sink::try_new(&self.handle) {
+            sink.set_volume(0.0);
+            sink.append(sound.source().repeat_infinite());
+            self.music.insert(id, sink);
+        }
+        id
+    }
+
+    fn set_music_volume(&mut self, id: MusicId, volume: f32) {
+        if let Some(sink) = self.music.get(&id) {
+            sink.set_volume(volume);
+        }
+    }
+
+    fn stop_music(&mut self, id: MusicId) {
+        if let Some(sink) = self.music.remove(&id) {
+            sink.stop();
+        }
+    }
+
+    fn update(&mut self) {
+        self.voices.retain(|sink| !sink.empty());
+    }
+}
+
+// Lo que el backend nulo registra en lugar de reproducir
+#[derive(Clone, Debug, PartialEq)]
+pub enum AudioEvent {
+    Played { sound: String, bus: Bus, volume: f32, emitter: Option<Emitter> },
+    MusicStarted { sound: String },
+    MusicStopped { sound: String },
+}
+
+// Registro compartido de eventos; se puede consultar después de entregar el
+// backend al motor
+#[derive(Clone, Default)]
+pub struct AudioLog(Rc<RefCell<Vec<AudioEvent>>>);
+
+impl AudioLog {
+    #[cfg(test)]
+    pub fn events(&self) -> Vec<AudioEvent> {
+        self.0.borrow().clone()
+    }
+
+    // Número de veces que se reprodujo el efecto cargado desde `sound`
+    #[cfg(test)]
+    pub fn count_played(&self, sound: &str) -> usize {
+        self.0
+            .borrow()
+            .iter()
+            .filter(|event| matches!(event, AudioEvent::Played { sound: name, .. } if name == sound))
+            .count()
+    }
+
+    fn push(&self, event: AudioEvent) {
+        self.0.borrow_mut().push(event);
+    }
+}
+
+// Backend sin dispositivo de salida para CI, sesiones SSH y pruebas
+pub struct NullBackend {
+    log: AudioLog,
+    music: HashMap<MusicId, String>,
+    next_music_id: MusicId,
+}
+
+impl NullBackend {
+    pub fn new() -> Self {
+        NullBackend {
+            log: AudioLog::default(),
+            music: HashMap::new(),
+            next_music_id: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn log(&self) -> AudioLog {
+        self.log.clone()
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn play(&mut self, sound: &Sound, bus: Bus, volume: f32, emitter: Option<&Emitter>) {
+        self.log.push(AudioEvent::Played {
+            sound: sound.name().to_string(),
+            bus,
+            volume,
+            emitter: emitter.copied(),
+        });
+    }
+
+    fn start_music(&mut self, sound: &Sound) -> MusicId {
+        let id = self.next_music_id;
+        self.next_music_id += 1;
+        self.music.insert(id, sound.name().to_string());
+        self.log.push(AudioEvent::MusicStarted { sound: sound.name().to_string() });
+        id
+    }
+
+    fn set_music_volume(&mut self, _id: MusicId, _volume: f32) {}
+
+    fn stop_music(&mut self, id: MusicId) {
+        if let Some(sound) = self.music.remove(&id) {
+            self.log.push(AudioEvent::MusicStopped { sound });
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::assets::Sound;
+use crate::audio_backend::{AudioBackend, AudioBackendKind, MusicId};
+
+use std::time::{Duration, Instant};
+
+// Variable de entorno para elegir el backend de audio: `auto`, `rodio` o
+// `null`. Tiene prioridad sobre la opción `audio_backend` de la configuración.
+const BACKEND_ENV: &str = "TRAPPED_AUDIO";
+
+// Canales de mezcla. Cada uno tiene su propio volumen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bus {
+    Music,
+    Sfx,
+    Ui,
+}
+
+impl Bus {
+    fn index(self) -> usize {
+        match self {
+            Bus::Music => 0,
+            Bus::Sfx => 1,
+            Bus::Ui => 2,
+        }
+    }
+}
+
+// Posición de un sonido respecto al oyente: `pan` va de -1.0 (izquierda) a
+// 1.0 (derecha), `gain` es la atenuación por distancia y `muffled` indica que
+// hay una pared entre el sonido y el oyente
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emitter {
+    pub pan: f32,
+    pub gain: f32,
+    pub muffled: bool,
+}
+
+// Pista de música en bucle con su fundido actual
+struct MusicTrack {
+    name: String,
+    id: MusicId,
+    gain: f32,
+    target: f32,
+    fade_speed: f32, // Ganancia por segundo
+}
+
+impl MusicTrack {
+    fn step(&mut self, dt: f32) {
+        // Un fundido de duración cero salta directamente al objetivo
+        let delta = if self.fade_speed.is_infinite() { f32::INFINITY } else { self.fade_speed * dt };
+        if self.gain < self.target {
+            self.gain = (self.gain + delta).min(self.target);
+        } else if self.gain > self.target {
+            self.gain = (self.gain - delta).max(self.target);
+        }
+    }
+}
+
+fn fade_speed(fade: Duration) -> f32 {
+    if fade.is_zero() {
+        f32::INFINITY
+    } else {
+        1.0 / fade.as_secs_f32()
+    }
+}
+
+// Motor de audio único del juego: música en bucle con fundidos cruzados y
+// efectos que se lanzan y se olvidan, de modo que varios pueden sonar a la
+// vez. La salida real la hace un `AudioBackend`.
+pub struct AudioEngine {
+    backend: Box<dyn AudioBackend>,
+    bus_volumes: [f32; 3],
+    music: Option<MusicTrack>,
+    fading_out: Vec<MusicTrack>,
+    last_update: Instant,
+}
+
+impl AudioEngine {
+    pub fn new(backend: Box<dyn AudioBackend>) -> Self {
+        AudioEngine {
+            backend,
+            bus_volumes: [0.5, 1.0, 1.0],
+            music: None,
+            fading_out: Vec::new(),
+            last_update: Instant::now(),
+        }
+    }
+
+    // Usa el backend `configured` salvo que `TRAPPED_AUDIO` indique otro; sin
+    // dispositivo de salida el juego sigue funcionando en silencio
+    pub fn from_env(configured: AudioBackendKind) -> Self {
+        let kind = match std::env::var(BACKEND_ENV) {
+            Ok(value) => AudioBackendKind::parse(&value).unwrap_or_else(|| {
+                eprintln!("warning: unknown {} value '{}'; using {}", BACKEND_ENV, value, configured.name());
+                configured
+            }),
+            Err(_) => configured,
+        };
+        AudioEngine::new(kind.create())
+    }
+
+    pub fn bus_volume(&self, bus: Bus) -> f32 {
+        self.bus_volumes[bus.index()]
+    }
+
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.bus_volumes[bus.index()] = volume.clamp(0.0, 1.0);
+        self.apply_music_volume();
+    }
+
+    // Reproduce un efecto sin bloquear
+    pub fn play(&mut self, sound: &Sound, bus: Bus, volume: f32) {
+        let volume = volume * self.bus_volume(bus);
+        self.backend.play(sound, bus, volume, None);
+    }
+
+    // Reproduce un efecto en estéreo según la posición del emisor
+    pub fn play_positional(&mut self, sound: &Sound, bus: Bus, volume: f32, emitter: &Emitter) {
+        if emitter.gain <= 0.0 {
+            return;
+        }
+
+        let volume = volume * emitter.gain * self.bus_volume(bus);
+        self.backend.play(sound, bus, volume, Some(emitter));
+    }
+
+    // Cambia la música con un fundido cruzado. Si `name` ya está sonando no
+    // se reinicia.
+    pub fn play_music(&mut self, name: &str, sound: &Sound, fade: Duration) {
+        if self.music.as_ref().is_some_and(|track| track.name == name) {
+            return;
+        }
+
+        self.stop_music(fade);
+
+        let mut track = MusicTrack {
+            name: name.to_string(),
+            id: self.backend.start_music(sound),
+            gain: 0.0,
+            target: 1.0,
+            fade_speed: fade_speed(fade),
+        };
+        track.step(0.0);
+        self.music = Some(track);
+        self.apply_music_volume();
+    }
+
+    pub fn stop_music(&mut self, fade: Duration) {
+        if let Some(mut track) = self.music.take() {
+            track.target = 0.0;
+            track.fade_speed = fade_speed(fade);
+            self.fading_out.push(track);
+        }
+    }
+
+    // Avanza los fundidos y libera los efectos que ya terminaron. Se llama
+    // una vez por frame.
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        if let Some(track) = self.music.as_mut() {
+            track.step(dt);
+        }
+        for track in &mut self.fading_out {
+            track.step(dt);
+        }
+        let backend = &mut self.backend;
+        self.fading_out.retain(|track| {
+            let playing = track.gain > 0.0;
+            if !playing {
+                backend.stop_music(track.id);
+            }
+            playing
+        });
+        self.backend.update();
+
+        self.apply_music_volume();
+    }
+
+    fn apply_music_volume(&mut self) {
+        let bus = self.bus_volume(Bus::Music);
+        for track in self.music.iter().chain(self.fading_out.iter()) {
+            self.backend.set_music_volume(track.id, track.gain * bus);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_backend::{AudioEvent, NullBackend};
+
+    fn null_engine() -> (AudioEngine, crate::audio_backend::AudioLog) {
+        let backend = NullBackend::new();
+        let log = backend.log();
+        (AudioEngine::new(Box::new(backend)), log)
+    }
+
+    #[test]
+    fn null_backend_records_effects_with_bus_volume() {
+        let (mut audio, log) = null_engine();
+        audio.set_bus_volume(Bus::Sfx, 0.5);
+        audio.play(&Sound::silence("Audio/Shot.wav"), Bus::Sfx, 0.8);
+
+        assert_eq!(
+            log.events(),
+            vec![AudioEvent::Played { sound: "Audio/Shot.wav".to_string(), bus: Bus::Sfx, volume: 0.4, emitter: None }]
+        );
+    }
+
+    #[test]
+    fn inaudible_positional_sounds_are_skipped() {
+        let (mut audio, log) = null_engine();
+        let emitter = Emitter { pan: 0.0, gain: 0.0, muffled: false };
+        audio.play_positional(&Sound::silence("Audio/Footsteps.wav"), Bus::Sfx, 1.0, &emitter);
+
+        assert_eq!(log.count_played("Audio/Footsteps.wav"), 0);
+    }
+
+    #[test]
+    fn music_switch_stops_the_previous_track() {
+        let (mut audio, log) = null_engine();
+        audio.play_music("title", &Sound::silence("Audio/Inicio.mp3"), Duration::ZERO);
+        audio.play_music("title", &Sound::silence("Audio/Inicio.mp3"), Duration::ZERO);
+        audio.play_music("gameplay", &Sound::silence("Audio/Juego.mp3"), Duration::ZERO);
+        audio.update();
+
+        assert_eq!(
+            log.events(),
+            vec![
+                AudioEvent::MusicStarted { sound: "Audio/Inicio.mp3".to_string() },
+                AudioEvent::MusicStarted { sound: "Audio/Juego.mp3".to_string() },
+                AudioEvent::MusicStopped { sound: "Audio/Inicio.mp3".to_string() },
+            ]
+        );
+    }
+}
@@ -1,34 +1,87 @@
 use std::fs::File;
 use std::io::{Write, BufWriter, Result};
 
-const BMP_HEADER_SIZE: usize = 54;
-const BMP_PIXEL_OFFSET: usize = 54;
-const BMP_BITS_PER_PIXEL: usize = 32;
+const FILE_HEADER_SIZE: usize = 14;
+const INFO_HEADER_SIZE: usize = 40; // BITMAPINFOHEADER
+const V4_HEADER_SIZE: usize = 108; // BITMAPV4HEADER
+
+// Tipos de compresión
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+
+const LCS_SRGB: u32 = 0x7352_4742; // 'sRGB'
+const PIXELS_PER_METER: u32 = 2835; // 72 DPI
+
+// Formato de los píxeles en el archivo
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BmpFormat {
+    // 24 bits sin alfa, filas rellenadas a múltiplos de 4 bytes. Para el
+    // framebuffer, que guarda 0RGB.
+    Rgb24,
+    // 32 bits con alfa y cabecera BITMAPV4 con las máscaras de cada canal.
+    // Para capas transparentes, que guardan ARGB.
+    Argb32,
+}
+
+impl BmpFormat {
+    fn bits_per_pixel(self) -> usize {
+        match self {
+            BmpFormat::Rgb24 => 24,
+            BmpFormat::Argb32 => 32,
+        }
+    }
+
+    fn header_size(self) -> usize {
+        match self {
+            BmpFormat::Rgb24 => INFO_HEADER_SIZE,
+            BmpFormat::Argb32 => V4_HEADER_SIZE,
+        }
+    }
+}
+
+// Bytes de una fila de píxeles, rellenada a un múltiplo de 4
+fn row_size(width: usize, bits_per_pixel: usize) -> usize {
+    (width * bits_per_pixel).div_ceil(32) * 4
+}
 
 pub fn write_bmp_file(
     file_path: &str,
     buffer: &[u32],
     width: usize,
     height: usize,
+    format: BmpFormat,
 ) -> Result<()> {
     let file = File::create(file_path)?;
     let mut writer = BufWriter::new(file);
 
-    write_bmp_header(&mut writer, width, height)?;
-    write_pixel_data(&mut writer, buffer, width, height)?;
+    write_bmp(&mut writer, buffer, width, height, format)?;
 
     writer.flush()?;
     Ok(())
 }
 
-fn write_bmp_header(
-    writer: &mut BufWriter<File>,
+// Escribe `buffer` (filas de arriba abajo, 0RGB o ARGB según `format`)
+pub fn write_bmp<W: Write>(
+    writer: &mut W,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    format: BmpFormat,
+) -> Result<()> {
+    write_bmp_header(writer, width, height, format)?;
+    write_pixel_data(writer, buffer, width, height, format)
+}
+
+fn write_bmp_header<W: Write>(
+    writer: &mut W,
     width: usize,
     height: usize,
+    format: BmpFormat,
 ) -> Result<()> {
-    let file_size = (BMP_HEADER_SIZE + (width * height * 4)) as u32;
+    let image_size = (row_size(width, format.bits_per_pixel()) * height) as u32;
+    let offset = (FILE_HEADER_SIZE + format.header_size()) as u32;
+    let file_size = offset + image_size;
     let reserved: u32 = 0;
-    let offset: u32 = BMP_PIXEL_OFFSET as u32;
 
     // BMP header
     writer.write_all(b"BM")?;
@@ -36,44 +89,300 @@ fn write_bmp_header(
     writer.write_all(&reserved.to_le_bytes())?;
     writer.write_all(&offset.to_le_bytes())?;
 
-    // DIB header
-    let header_size: u32 = 40;
+    // DIB header; la altura positiva indica filas de abajo arriba
     let planes: u16 = 1;
-    let bpp: u16 = BMP_BITS_PER_PIXEL as u16;
-    let compression: u32 = 0;
-    let image_size: u32 = (width * height * 4) as u32;
-    let ppm: u32 = 2835; // 72 DPI
-
-    writer.write_all(&header_size.to_le_bytes())?;
-    writer.write_all(&(width as u32).to_le_bytes())?;
-    writer.write_all(&(height as u32).to_le_bytes())?;
+    let bpp = format.bits_per_pixel() as u16;
+    let compression = match format {
+        BmpFormat::Rgb24 => BI_RGB,
+        BmpFormat::Argb32 => BI_BITFIELDS,
+    };
+
+    writer.write_all(&(format.header_size() as u32).to_le_bytes())?;
+    writer.write_all(&(width as i32).to_le_bytes())?;
+    writer.write_all(&(height as i32).to_le_bytes())?;
     writer.write_all(&planes.to_le_bytes())?;
     writer.write_all(&bpp.to_le_bytes())?;
     writer.write_all(&compression.to_le_bytes())?;
     writer.write_all(&image_size.to_le_bytes())?;
-    writer.write_all(&ppm.to_le_bytes())?;
-    writer.write_all(&ppm.to_le_bytes())?;
+    writer.write_all(&PIXELS_PER_METER.to_le_bytes())?;
+    writer.write_all(&PIXELS_PER_METER.to_le_bytes())?;
     writer.write_all(&0u32.to_le_bytes())?; // Number of colors
     writer.write_all(&0u32.to_le_bytes())?; // Important colors
 
+    if format == BmpFormat::Argb32 {
+        // Máscaras de rojo, verde, azul y alfa
+        for mask in [0x00FF_0000u32, 0x0000_FF00, 0x0000_00FF, 0xFF00_0000] {
+            writer.write_all(&mask.to_le_bytes())?;
+        }
+        writer.write_all(&LCS_SRGB.to_le_bytes())?;
+        // Extremos del espacio de color y gamma: sin uso con sRGB
+        writer.write_all(&[0; 36 + 12])?;
+    }
+
     Ok(())
 }
 
-fn write_pixel_data(
-    writer: &mut BufWriter<File>,
+fn write_pixel_data<W: Write>(
+    writer: &mut W,
     buffer: &[u32],
     width: usize,
     height: usize,
+    format: BmpFormat,
 ) -> Result<()> {
+    let mut row = Vec::with_capacity(row_size(width, format.bits_per_pixel()));
     for y in (0..height).rev() {
-        for x in 0..width {
-            let pixel = buffer[y * width + x];
-            let b = (pixel & 0xFF) as u8;
-            let g = ((pixel >> 8) & 0xFF) as u8;
-            let r = ((pixel >> 16) & 0xFF) as u8;
-            let a = ((pixel >> 24) & 0xFF) as u8;
-            writer.write_all(&[b, g, r, a])?;
+        row.clear();
+        for &pixel in &buffer[y * width..(y + 1) * width] {
+            match format {
+                BmpFormat::Rgb24 => row.extend_from_slice(&pixel.to_le_bytes()[..3]),
+                BmpFormat::Argb32 => row.extend_from_slice(&pixel.to_le_bytes()),
+            }
         }
+        row.resize(row_size(width, format.bits_per_pixel()), 0);
+        writer.write_all(&row)?;
     }
     Ok(())
 }
+
+// Lectura de BMP. Por ahora solo la usan las pruebas de imágenes de
+// referencia (`golden`), así que no se compila en el juego.
+#[cfg(test)]
+pub mod reader {
+    use super::*;
+    use std::{fmt, io};
+
+    pub(super) const V5_HEADER_SIZE: usize = 124; // BITMAPV5HEADER
+    const CORE_HEADER_SIZE: usize = 12; // BITMAPCOREHEADER (OS/2)
+    const BI_ALPHABITFIELDS: u32 = 6;
+
+    // Imagen leída de un BMP. `pixels` va de arriba abajo en 0RGB, o en ARGB si
+    // el archivo tiene canal alfa (`format` es `Argb32`).
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct BmpImage {
+        pub width: usize,
+        pub height: usize,
+        pub pixels: Vec<u32>,
+        pub format: BmpFormat,
+    }
+
+    #[derive(Debug)]
+    pub enum BmpError {
+        Io(io::Error),
+        Invalid(&'static str),
+        Unsupported(String),
+    }
+
+    impl fmt::Display for BmpError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                BmpError::Io(e) => write!(f, "could not read BMP: {}", e),
+                BmpError::Invalid(reason) => write!(f, "invalid BMP: {}", reason),
+                BmpError::Unsupported(what) => write!(f, "unsupported BMP: {}", what),
+            }
+        }
+    }
+
+    impl From<io::Error> for BmpError {
+        fn from(e: io::Error) -> Self {
+            BmpError::Io(e)
+        }
+    }
+
+    pub fn read_bmp_file(file_path: &str) -> std::result::Result<BmpImage, BmpError> {
+        read_bmp(&std::fs::read(file_path)?)
+    }
+
+    // Lee BMP de 24 y 32 bits sin comprimir o con máscaras de canal, con
+    // cabeceras BITMAPCOREHEADER, BITMAPINFOHEADER, V4 o V5 y filas en
+    // cualquiera de los dos sentidos
+    pub fn read_bmp(data: &[u8]) -> std::result::Result<BmpImage, BmpError> {
+        let u16_at = |offset: usize| data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or(BmpError::Invalid("truncated header"));
+        let u32_at = |offset: usize| data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or(BmpError::Invalid("truncated header"));
+
+        if !data.starts_with(b"BM") {
+            return Err(BmpError::Invalid("missing 'BM' signature"));
+        }
+        let pixel_offset = u32_at(10)? as usize;
+        let header_size = u32_at(FILE_HEADER_SIZE)? as usize;
+        let header = FILE_HEADER_SIZE;
+
+        let (width, height, bits_per_pixel, compression) = match header_size {
+            CORE_HEADER_SIZE => (u16_at(header + 4)? as i64, u16_at(header + 6)? as i64, u16_at(header + 10)?, BI_RGB),
+            INFO_HEADER_SIZE | 52 | 56 | V4_HEADER_SIZE | V5_HEADER_SIZE => (
+                u32_at(header + 4)? as i32 as i64,
+                u32_at(header + 8)? as i32 as i64,
+                u16_at(header + 14)?,
+                u32_at(header + 16)?,
+            ),
+            _ => return Err(BmpError::Unsupported(format!("{}-byte header", header_size))),
+        };
+
+        let bits_per_pixel = bits_per_pixel as usize;
+        if bits_per_pixel != 24 && bits_per_pixel != 32 {
+            return Err(BmpError::Unsupported(format!("{} bits per pixel", bits_per_pixel)));
+        }
+        if width <= 0 || height == 0 {
+            return Err(BmpError::Invalid("empty image"));
+        }
+
+        // Máscaras de rojo, verde, azul y alfa. En BITMAPINFOHEADER van justo
+        // después de la cabecera; en las versiones posteriores, dentro de ella.
+        let masks = match compression {
+            BI_RGB => [0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0],
+            BI_BITFIELDS | BI_ALPHABITFIELDS if bits_per_pixel == 32 => {
+                let start = header + 40;
+                let has_alpha = header_size >= 56 || compression == BI_ALPHABITFIELDS;
+                [u32_at(start)?, u32_at(start + 4)?, u32_at(start + 8)?, if has_alpha { u32_at(start + 12)? } else { 0 }]
+            }
+            _ => return Err(BmpError::Unsupported(format!("compression {}", compression))),
+        };
+
+        let width = width as usize;
+        let top_down = height < 0;
+        let height = height.unsigned_abs() as usize;
+        let stride = row_size(width, bits_per_pixel);
+        let pixel_bytes = stride.checked_mul(height).ok_or(BmpError::Invalid("image too large"))?;
+        let pixel_data = data
+            .get(pixel_offset..)
+            .filter(|pixels| pixels.len() >= pixel_bytes)
+            .ok_or(BmpError::Invalid("truncated pixel data"))?;
+
+        // En u64: con máscaras de más de 24 bits el producto no cabe en u32
+        let channel = |value: u32, mask: u32| -> u32 {
+            if mask == 0 {
+                return 0;
+            }
+            let shift = mask.trailing_zeros();
+            let max = (mask >> shift) as u64;
+            (((value & mask) >> shift) as u64 * 255 / max) as u32
+        };
+
+        let bytes_per_pixel = bits_per_pixel / 8;
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let file_row = if top_down { y } else { height - 1 - y };
+            let row = &pixel_data[file_row * stride..file_row * stride + width * bytes_per_pixel];
+            for bytes in row.chunks_exact(bytes_per_pixel) {
+                let value = match bytes_per_pixel {
+                    3 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]),
+                    _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                };
+                pixels.push(
+                    channel(value, masks[3]) << 24
+                        | channel(value, masks[0]) << 16
+                        | channel(value, masks[1]) << 8
+                        | channel(value, masks[2]),
+                );
+            }
+        }
+
+        Ok(BmpImage {
+            width,
+            height,
+            pixels,
+            format: if masks[3] != 0 { BmpFormat::Argb32 } else { BmpFormat::Rgb24 },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::reader::*;
+
+    fn encode(buffer: &[u32], width: usize, height: usize, format: BmpFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_bmp(&mut data, buffer, width, height, format).unwrap();
+        data
+    }
+
+    #[test]
+    fn rgb24_rows_are_padded_and_round_trip() {
+        // 3 píxeles de ancho: 9 bytes por fila más 3 de relleno
+        let buffer = [0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0x0012_3456, 0x00FF_FFFF, 0x0000_0000];
+        let data = encode(&buffer, 3, 2, BmpFormat::Rgb24);
+
+        assert_eq!(data.len(), 54 + 2 * 12);
+        assert_eq!(u32::from_le_bytes(data[2..6].try_into().unwrap()) as usize, data.len());
+        // La última fila va primero en el archivo
+        assert_eq!(&data[54..63], &[0x56, 0x34, 0x12, 0xFF, 0xFF, 0xFF, 0, 0, 0]);
+
+        let image = read_bmp(&data).unwrap();
+        assert_eq!((image.width, image.height, image.format), (3, 2, BmpFormat::Rgb24));
+        assert_eq!(image.pixels, buffer);
+    }
+
+    #[test]
+    fn argb32_uses_a_v4_header_and_keeps_alpha() {
+        let buffer = [0x80FF_0000, 0x0000_0000, 0xFF00_FF00, 0x4012_3456];
+        let data = encode(&buffer, 2, 2, BmpFormat::Argb32);
+
+        assert_eq!(u32::from_le_bytes(data[14..18].try_into().unwrap()) as usize, V4_HEADER_SIZE);
+        assert_eq!(u32::from_le_bytes(data[10..14].try_into().unwrap()) as usize, 14 + V4_HEADER_SIZE);
+
+        let image = read_bmp(&data).unwrap();
+        assert_eq!(image.format, BmpFormat::Argb32);
+        assert_eq!(image.pixels, buffer);
+    }
+
+    #[test]
+    fn reads_v5_headers_and_top_down_rows() {
+        let buffer = [0x80FF_0000, 0x0000_0000, 0xFF00_FF00, 0x4012_3456];
+
+        // V5: la cabecera V4 más 16 bytes (intención, perfil y reservado)
+        let v4 = encode(&buffer, 2, 2, BmpFormat::Argb32);
+        let mut v5 = v4[..14 + V4_HEADER_SIZE].to_vec();
+        v5.extend_from_slice(&[0; 16]);
+        v5.extend_from_slice(&v4[14 + V4_HEADER_SIZE..]);
+        v5[10..14].copy_from_slice(&((14 + V5_HEADER_SIZE) as u32).to_le_bytes());
+        v5[14..18].copy_from_slice(&(V5_HEADER_SIZE as u32).to_le_bytes());
+        assert_eq!(read_bmp(&v5).unwrap().pixels, buffer);
+
+        // 32 bits sin máscaras y con altura negativa, como el antiguo formato
+        // de `save_as_bmp` pero sin invertir las filas
+        let mut top_down = encode(&buffer, 2, 2, BmpFormat::Rgb24)[..54].to_vec();
+        top_down[22..26].copy_from_slice(&(-2i32).to_le_bytes());
+        top_down[28..30].copy_from_slice(&32u16.to_le_bytes());
+        for pixel in buffer {
+            top_down.extend_from_slice(&pixel.to_le_bytes());
+        }
+        let image = read_bmp(&top_down).unwrap();
+        assert_eq!(image.format, BmpFormat::Rgb24);
+        assert_eq!(image.pixels, buffer.map(|pixel| pixel & 0x00FF_FFFF));
+    }
+
+    #[test]
+    fn rejects_files_it_cannot_read() {
+        assert!(matches!(read_bmp(b"PNG"), Err(BmpError::Invalid(_))));
+
+        let mut paletted = encode(&[0; 4], 2, 2, BmpFormat::Rgb24);
+        paletted[28..30].copy_from_slice(&8u16.to_le_bytes());
+        assert!(matches!(read_bmp(&paletted), Err(BmpError::Unsupported(_))));
+
+        let truncated = encode(&[0; 4], 2, 2, BmpFormat::Rgb24);
+        assert!(matches!(read_bmp(&truncated[..60]), Err(BmpError::Invalid(_))));
+
+        // Dimensiones enormes: se rechazan sin reservar memoria
+        let mut huge = encode(&[0; 4], 2, 2, BmpFormat::Rgb24);
+        huge[18..22].copy_from_slice(&i32::MAX.to_le_bytes());
+        huge[22..26].copy_from_slice(&i32::MIN.to_le_bytes());
+        huge[28..30].copy_from_slice(&32u16.to_le_bytes());
+        assert!(matches!(read_bmp(&huge), Err(BmpError::Invalid(_))));
+    }
+
+    #[test]
+    fn wide_channel_masks_do_not_overflow() {
+        // Una sola máscara de 32 bits para el rojo: 0xFFFFFFFF es el máximo
+        let buffer = [0x00FF_0000, 0x0000_0000];
+        let mut data = encode(&buffer, 2, 1, BmpFormat::Argb32);
+        data[30..34].copy_from_slice(&BI_BITFIELDS.to_le_bytes());
+        for (i, mask) in [u32::MAX, 0, 0, 0].iter().enumerate() {
+            data[54 + i * 4..58 + i * 4].copy_from_slice(&mask.to_le_bytes());
+        }
+        let pixels = 14 + V4_HEADER_SIZE;
+        data[pixels..pixels + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let image = read_bmp(&data).unwrap();
+        assert_eq!(image.pixels, [0x00FF_0000, 0x0000_0000]);
+    }
+}
@@ -0,0 +1,371 @@
+use crate::assets::{AssetError, Assets};
+use crate::doors::{load_exit_texture, Doors};
+use crate::enemy::Enemy;
+use crate::exploration::Exploration;
+use crate::framebuffer::Framebuffer;
+use crate::layer::CachedLayer;
+use crate::maze::{draw_enemies_position, draw_player_position, is_wall, minimap, render, render_enemies_pos, render_view, MapTransform};
+use crate::occupancy::Occupancy;
+use crate::player::Player;
+use crate::scenes::LEVELS;
+use crate::settings::Settings;
+use crate::sprite::{Clip, SpriteSheet};
+
+use nalgebra_glm::Vec2;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Carpeta de las capturas de pantalla, relativa al directorio de trabajo
+const SCREENSHOT_DIR: &str = "screenshots";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    Bmp,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "bmp" => Some(ImageFormat::Bmp),
+            "png" => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+// Guarda el contenido del framebuffer en `path`
+pub fn save_image(framebuffer: &Framebuffer, path: &Path, format: ImageFormat) -> io::Result<()> {
+    match format {
+        ImageFormat::Bmp => framebuffer.save_as_bmp(&path.to_string_lossy()),
+        ImageFormat::Png => {
+            let width = framebuffer.get_width();
+            let buffer = framebuffer.get_buffer();
+            let image = image::RgbImage::from_fn(width as u32, framebuffer.get_height() as u32, |x, y| {
+                let pixel = buffer[y as usize * width + x as usize];
+                image::Rgb([(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+            });
+            image.save(path).map_err(io::Error::other)
+        }
+    }
+}
+
+// Fecha y hora UTC de `since_epoch` como `AAAAMMDD-HHMMSS-mmm`
+fn format_timestamp(since_epoch: Duration) -> String {
+    let seconds = since_epoch.as_secs();
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // Días desde 1970-01-01 a fecha civil (calendario gregoriano)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+// Guarda una captura con la fecha y hora en el nombre. Devuelve la ruta.
+pub fn save_screenshot(framebuffer: &Framebuffer) -> io::Result<PathBuf> {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    std::fs::create_dir_all(SCREENSHOT_DIR)?;
+    let path = Path::new(SCREENSHOT_DIR).join(format!("screenshot-{}.bmp", format_timestamp(since_epoch)));
+    save_image(framebuffer, &path, ImageFormat::Bmp)?;
+    Ok(path)
+}
+
+// Opciones del modo sin ventana `--capture-frames N`
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptureOptions {
+    pub frames: usize,
+    pub level: usize, // Índice en `LEVELS`
+    pub dir: PathBuf,
+    pub format: ImageFormat,
+}
+
+impl CaptureOptions {
+    // `None` si no se pidió el modo de captura. Opciones:
+    // `--capture-frames N [--level 1-3] [--capture-dir DIR] [--capture-format bmp|png]`
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        let mut frames = None;
+        let mut options = CaptureOptions {
+            frames: 0,
+            level: 0,
+            dir: PathBuf::from("captures"),
+            format: ImageFormat::Png,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--capture-frames" => {
+                    let value = value()?;
+                    frames = Some(value.parse().map_err(|_| format!("invalid frame count '{}'", value))?);
+                }
+                "--level" => {
+                    let value = value()?;
+                    options.level = match value.parse::<usize>() {
+                        Ok(level) if (1..=LEVELS.len()).contains(&level) => level - 1,
+                        _ => return Err(format!("invalid level '{}' (expected 1-{})", value, LEVELS.len())),
+                    };
+                }
+                "--capture-dir" => options.dir = PathBuf::from(value()?),
+                "--capture-format" => {
+                    let value = value()?;
+                    options.format = ImageFormat::parse(value).ok_or_else(|| format!("invalid capture format '{}' (expected bmp or png)", value))?;
+                }
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+
+        Ok(frames.map(|frames| CaptureOptions { frames, ..options }))
+    }
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Asset(AssetError),
+    Io(io::Error),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CaptureError::Asset(e) => write!(f, "{}", e),
+            CaptureError::Io(e) => write!(f, "could not write capture: {}", e),
+        }
+    }
+}
+
+impl From<AssetError> for CaptureError {
+    fn from(e: AssetError) -> Self {
+        CaptureError::Asset(e)
+    }
+}
+
+impl From<io::Error> for CaptureError {
+    fn from(e: io::Error) -> Self {
+        CaptureError::Io(e)
+    }
+}
+
+// Recorrido de cámara por el centro de las celdas
+pub struct CameraPath {
+    points: Vec<Vec2>,
+    // Hacia dónde se mira al final del recorrido
+    target: Option<Vec2>,
+}
+
+impl CameraPath {
+    // Camino más corto desde `start` hasta la celda libre junto a la salida
+    // ('/') más cercana. Si no hay salida alcanzable, la cámara gira en el sitio.
+    pub fn to_exit(maze: &Vec<Vec<char>>, start: Vec2, block_size: f32) -> Self {
+        let center = |(col, row): (usize, usize)| Vec2::new((col as f32 + 0.5) * block_size, (row as f32 + 0.5) * block_size);
+        let start_cell = ((start.x / block_size) as usize, (start.y / block_size) as usize);
+        let rows = maze.len();
+        let cols = maze.iter().map(|row| row.len()).max().unwrap_or(0);
+
+        let neighbours = |(col, row): (usize, usize)| {
+            [(0isize, -1isize), (1, 0), (0, 1), (-1, 0)]
+                .iter()
+                .map(move |(dx, dy)| (col as isize + dx, row as isize + dy))
+                .filter(move |&(c, r)| c >= 0 && r >= 0 && (c as usize) < cols && (r as usize) < rows)
+                .map(|(c, r)| (c as usize, r as usize))
+        };
+        let cell = |(col, row): (usize, usize)| maze[row].get(col).copied().unwrap_or(' ');
+
+        let mut previous = vec![None; cols * rows];
+        let mut visited = vec![false; cols * rows];
+        let mut queue = VecDeque::from([start_cell]);
+        visited[start_cell.1 * cols + start_cell.0] = true;
+
+        while let Some(current) = queue.pop_front() {
+            if let Some(exit) = neighbours(current).find(|&next| cell(next) == '/') {
+                let mut cells = vec![current];
+                while let Some(before) = previous[cells[cells.len() - 1].1 * cols + cells[cells.len() - 1].0] {
+                    cells.push(before);
+                }
+                cells.reverse();
+
+                let mut points = vec![start];
+                points.extend(cells.into_iter().skip(1).map(center));
+                return CameraPath { points, target: Some(center(exit)) };
+            }
+
+            for next in neighbours(current) {
+                let index = next.1 * cols + next.0;
+                if !visited[index] && !is_wall(maze, next.0, next.1).0 {
+                    visited[index] = true;
+                    previous[index] = Some(current);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        CameraPath { points: vec![start], target: None }
+    }
+
+    fn length(&self) -> f32 {
+        self.points.windows(2).map(|pair| (pair[1] - pair[0]).norm()).sum()
+    }
+
+    // Punto a `distance` del inicio, medido sobre el recorrido
+    fn point_at(&self, distance: f32) -> Vec2 {
+        let mut remaining = distance.max(0.0);
+        for pair in self.points.windows(2) {
+            let segment = (pair[1] - pair[0]).norm();
+            if remaining <= segment && segment > 0.0 {
+                return pair[0] + (pair[1] - pair[0]) * (remaining / segment);
+            }
+            remaining -= segment;
+        }
+        self.points[self.points.len() - 1]
+    }
+
+    // Posición y ángulo de la cámara en `t` (0.0 al inicio, 1.0 al final). La
+    // cámara mira un poco por delante de su posición para suavizar los giros.
+    pub fn sample(&self, t: f32, look_ahead: f32) -> (Vec2, f32) {
+        let t = t.clamp(0.0, 1.0);
+        let length = self.length();
+        if length == 0.0 {
+            return (self.points[0], t * std::f32::consts::TAU);
+        }
+
+        let position = self.point_at(t * length);
+        let mut ahead = self.point_at(t * length + look_ahead);
+        if (ahead - position).norm() < 1.0 {
+            ahead = self.target.unwrap_or(ahead);
+        }
+        (position, (ahead.y - position.y).atan2(ahead.x - position.x))
+    }
+}
+
+// Renderiza sin ventana `options.frames` imágenes numeradas de un recorrido
+// desde la aparición del jugador hasta la salida. Los guardias se quedan en su
+// posición inicial y las puertas abiertas para que la misma ruta dé siempre
+// las mismas imágenes.
+pub fn capture_frames(options: &CaptureOptions, settings: &Settings, assets: &mut Assets) -> Result<(), CaptureError> {
+    let mut framebuffer = Framebuffer::new(settings.width, settings.height);
+    let view_width = ((settings.width as f32 * settings.render_scale).round() as usize).max(1);
+    let view_height = ((settings.height as f32 * settings.render_scale).round() as usize).max(1);
+    let mut view = Framebuffer::new(view_width, view_height);
+
+    let level = assets.maze(LEVELS[options.level])?;
+    let (mut maze, spawn) = render(&mut framebuffer, &level, 0.5);
+    let block_size = std::cmp::min(framebuffer.get_width() / maze[0].len(), framebuffer.get_height() / maze.len()) as f32;
+    let mut doors = Doors::new(&maze);
+    doors.open_all(&mut maze);
+    let enemies: Vec<Enemy> = render_enemies_pos(&mut framebuffer, &level)
+        .into_iter()
+        .map(|pos| Enemy::new(pos, 0.0, 0.0, 22.5, 100.0))
+        .collect();
+
+    let texture = assets.texture("textures/prison_wall.png");
+    let texture_cell = assets.texture("textures/Cell.png");
+    let texture_door = assets.texture("textures/Door.jpeg");
+    let texture_exit = load_exit_texture(assets, &texture_door);
+    let guard_sheet = SpriteSheet::guards(assets);
+    let clips: Vec<Clip> = enemies.iter().map(|enemy| Clip::for_guard(enemy, false, false)).collect();
+
+    let path = CameraPath::to_exit(&maze, spawn, block_size);
+    let mut occupancy = Occupancy::new(block_size);
+    let mut exploration = Exploration::new(&maze, enemies.len());
+    let mut minimap_layer = CachedLayer::new();
+    let transform = MapTransform::corner(block_size);
+
+    std::fs::create_dir_all(&options.dir)?;
+    for frame in 0..options.frames {
+        let t = if options.frames > 1 { frame as f32 / (options.frames - 1) as f32 } else { 0.0 };
+        let (pos, angle) = path.sample(t, block_size);
+        let player = Player::new(pos.x, pos.y, angle, settings.fov_radians());
+
+        // El reloj de la animación avanza a 30 frames por segundo
+        let guards = guard_sheet.guard_sprites(&enemies, &clips, player.pos, frame as f32 / 30.0);
+        render_view(&mut view, &player, &maze, block_size, [&texture, &texture_cell, &texture_door, &texture_exit], &doors, &guards);
+        framebuffer.draw_scaled(&view);
+
+        occupancy.rebuild(player.pos, &enemies);
+        minimap(&mut framebuffer, &mut minimap_layer, &maze, 0.5, &occupancy, &mut exploration, &transform);
+        draw_enemies_position(&mut framebuffer, &enemies, &occupancy, &transform);
+        draw_player_position(&mut framebuffer, player.pos, &transform);
+
+        let file = options.dir.join(format!("frame-{:04}.{}", frame, options.format.extension()));
+        save_image(&framebuffer, &file, options.format)?;
+    }
+
+    println!("captured {} frames to '{}'", options.frames, options.dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn timestamps_are_utc_calendar_dates() {
+        assert_eq!(format_timestamp(Duration::from_millis(0)), "19700101-000000-000");
+        // 2024-02-29 23:59:58.250 UTC
+        assert_eq!(format_timestamp(Duration::from_millis(1_709_251_198_250)), "20240229-235958-250");
+    }
+
+    #[test]
+    fn capture_arguments_are_optional_and_validated() {
+        assert_eq!(CaptureOptions::from_args(&args("")), Ok(None));
+
+        let options = CaptureOptions::from_args(&args("--capture-frames 12 --level 3 --capture-format bmp --capture-dir out")).unwrap().unwrap();
+        assert_eq!(options, CaptureOptions { frames: 12, level: 2, dir: PathBuf::from("out"), format: ImageFormat::Bmp });
+
+        assert!(CaptureOptions::from_args(&args("--capture-frames many")).is_err());
+        assert!(CaptureOptions::from_args(&args("--capture-frames 3 --level 4")).is_err());
+        assert!(CaptureOptions::from_args(&args("--capture-frames")).is_err());
+    }
+
+    #[test]
+    fn the_camera_walks_to_the_exit() {
+        let maze: Vec<Vec<char>> = ["+-----+", "|  |  |", "|     /", "+-----+"].iter().map(|row| row.chars().collect()).collect();
+        let start = Vec2::new(15.0, 15.0);
+        let path = CameraPath::to_exit(&maze, start, 10.0);
+
+        let (first, _) = path.sample(0.0, 10.0);
+        assert_eq!(first, start);
+
+        // Termina junto a la salida, mirándola
+        let (last, angle) = path.sample(1.0, 10.0);
+        assert_eq!(last, Vec2::new(55.0, 25.0));
+        assert!(angle.abs() < 1e-4);
+
+        // Sin salida gira en el sitio
+        let closed: Vec<Vec<char>> = ["+---+", "|   |", "+---+"].iter().map(|row| row.chars().collect()).collect();
+        let path = CameraPath::to_exit(&closed, Vec2::new(15.0, 15.0), 10.0);
+        assert_eq!(path.sample(0.5, 10.0), (Vec2::new(15.0, 15.0), std::f32::consts::PI));
+    }
+}
@@ -1,6 +1,6 @@
 use nalgebra_glm::Vec2;
-use crate::color::Color;
 use crate::framebuffer::Framebuffer;
+use crate::doors::OPEN_DOOR;
 
 pub struct Intersect {
     pub x: f32,
@@ -42,8 +42,9 @@ pub fn cast_ray(
         // Obtener el carácter en la celda
         let cell_char = maze[j as usize][i as usize];
 
-        // Verificar si la celda no es un espacio vacío y no es el punto 'p'
-        if cell_char != ' ' && cell_char != 'p' && cell_char != 'e' {
+        // El mapa de tiles no contiene entidades: cualquier celda no vacía es
+        // pared, salvo las puertas abiertas del todo
+        if cell_char != ' ' && cell_char != OPEN_DOOR {
             return Some(Intersect {
                 x,
                 y,
@@ -72,65 +73,3 @@ pub fn normalize_angle(angle: f32) -> f32 {
     let two_pi = std::f32::consts::PI * 2.0;
     (angle + two_pi) % two_pi
 }
-
-pub fn cast_ray_enemy(
-    player_pos: &Vec2,
-    direction: f32,
-    maze: &Vec<Vec<char>>,
-    block_size: f32,
-    draw_line: bool,
-    max_distance: f32,
-    scale: f32,
-    mut framebuffer: Option<&mut Framebuffer>,
-) -> Option<Intersect> {
-    let mut d = 0.0;
-
-    let cos = direction.cos();
-    let sin = direction.sin();
-
-    loop {
-        let x = (player_pos.x * scale + cos * d) as usize;
-        let y = (player_pos.y * scale + sin * d) as usize;
-
-        let i = (x as f32 / block_size).floor() as usize;
-        let j = (y as f32 / block_size).floor() as usize;
-
-        if j >= maze.len() || i >= maze[0].len() {
-            return None;
-        }
-
-
-            if let Some(fb) = framebuffer.as_deref() {
-                if let Some(hex_color) = fb.get_point(x as isize, y as isize) {
-                    let r = ((hex_color >> 16) & 0xFF) as i32;
-                    let g = ((hex_color >> 8) & 0xFF) as i32;
-                    let b = (hex_color & 0xFF) as i32;
-                    
-                    let color = Color::new(r, g, b);
-
-                    // Verificar si el color es solo rojo
-                    if color.match_rgb() {
-                        return Some(Intersect {
-                            x: x as f32,
-                            y: y as f32,
-                            distance: d,
-                            character: '\0',
-                        });
-                    }
-                }
-            }
-        
-
-        if draw_line {
-            if let Some(fb) = framebuffer.as_deref_mut() {
-                fb.point(x as isize, y as isize);
-            }
-        }
-
-        d += 1.0;
-
-        if d > max_distance * scale {
-            return None;
-        }
-    }
-}
\ No newline at end of file
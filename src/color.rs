@@ -5,17 +5,39 @@ pub struct Color {
     r: u8,
     g: u8,
     b: u8,
+    a: u8,
 }
 
 impl Color {
     pub fn new(r: i32, g: i32, b: i32) -> Self {
+        Color::new_rgba(r, g, b, 255)
+    }
+
+    pub fn new_rgba(r: i32, g: i32, b: i32, a: i32) -> Self {
         Color {
             r: r.clamp(0, 255) as u8,
             g: g.clamp(0, 255) as u8,
             b: b.clamp(0, 255) as u8,
+            a: a.clamp(0, 255) as u8,
         }
     }
 
+    pub fn transparent() -> Self {
+        Color::new_rgba(0, 0, 0, 0)
+    }
+
+    pub fn alpha(&self) -> u8 {
+        self.a
+    }
+
+    pub fn with_alpha(self, a: u8) -> Color {
+        Color { a, ..self }
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.a == 255
+    }
+
     pub fn from_hex(hex: u32) -> Color {
         let r = ((hex >> 16) & 0xFF) as u8;
         let g = ((hex >> 8) & 0xFF) as u8;
@@ -23,25 +45,64 @@ impl Color {
         Color::new(r.into(), g.into(), b.into())
     }
 
+    // El framebuffer guarda 0RGB: el alfa no se escribe en el byte alto
     pub fn to_hex(&self) -> u32 {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
 
-    pub fn match_rgb(&self) -> bool {
-        self.g == 166 && self.b == 114 && self.r == 5
+    // Capas transparentes: el alfa se guarda en el byte alto (ARGB)
+    pub fn from_argb(argb: u32) -> Color {
+        Color { a: (argb >> 24) as u8, ..Color::from_hex(argb) }
+    }
+
+    pub fn to_argb(&self) -> u32 {
+        ((self.a as u32) << 24) | self.to_hex()
+    }
+
+    // Igual que `over` sobre un píxel opaco del framebuffer, pero en aritmética
+    // entera y sin pasar por `Color`
+    pub fn over_hex(self, dst: u32) -> u32 {
+        let a = self.a as u32;
+        let inv = 255 - a;
+        let channel = |s: u8, shift: u32| -> u32 {
+            let d = (dst >> shift) & 0xFF;
+            ((s as u32 * a + d * inv + 127) / 255) << shift
+        };
+        channel(self.r, 16) | channel(self.g, 8) | channel(self.b, 0)
+    }
+
+    // Composición "source over": dibuja `self` con su alfa encima de `dst`
+    pub fn over(self, dst: Color) -> Color {
+        let src_a = self.a as f32 / 255.0;
+        let dst_a = dst.a as f32 / 255.0;
+        let out_a = src_a + dst_a * (1.0 - src_a);
+        if out_a <= 0.0 {
+            return Color::transparent();
+        }
+
+        let channel = |s: u8, d: u8| -> i32 {
+            ((s as f32 * src_a + d as f32 * dst_a * (1.0 - src_a)) / out_a).round() as i32
+        };
+        Color::new_rgba(
+            channel(self.r, dst.r),
+            channel(self.g, dst.g),
+            channel(self.b, dst.b),
+            (out_a * 255.0).round() as i32,
+        )
     }
 
     pub fn blend(self, other: Color, opacity: f32) -> Color {
         let r = (self.r as f32 * (1.0 - opacity) + other.r as f32 * opacity).clamp(0.0, 255.0) as u8;
         let g = (self.g as f32 * (1.0 - opacity) + other.g as f32 * opacity).clamp(0.0, 255.0) as u8;
         let b = (self.b as f32 * (1.0 - opacity) + other.b as f32 * opacity).clamp(0.0, 255.0) as u8;
-        Color::new(r.into(), g.into(), b.into())
+        let a = (self.a as f32 * (1.0 - opacity) + other.a as f32 * opacity).clamp(0.0, 255.0) as u8;
+        Color::new_rgba(r.into(), g.into(), b.into(), a.into())
     }
 }
 
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+        write!(f, "Color(r: {}, g: {}, b: {}, a: {})", self.r, self.g, self.b, self.a)
     }
 }
 
@@ -52,7 +113,7 @@ impl std::ops::Add for Color {
         let r = self.r.saturating_add(other.r);
         let g = self.g.saturating_add(other.g);
         let b = self.b.saturating_add(other.b);
-        Color::new(r.into(), g.into(), b.into())
+        Color::new_rgba(r.into(), g.into(), b.into(), self.a.into())
     }
 }
 
@@ -63,7 +124,7 @@ impl std::ops::Mul<f32> for Color {
         let r = (self.r as f32 * factor).clamp(0.0, 255.0) as u8;
         let g = (self.g as f32 * factor).clamp(0.0, 255.0) as u8;
         let b = (self.b as f32 * factor).clamp(0.0, 255.0) as u8;
-        Color::new(r.into(), g.into(), b.into())
+        Color::new_rgba(r.into(), g.into(), b.into(), self.a.into())
     }
 }
 
@@ -74,7 +135,7 @@ impl std::ops::Sub for Color {
         let r = (self.r as i16 - other.r as i16).clamp(0, 255) as u8;
         let g = (self.g as i16 - other.g as i16).clamp(0, 255) as u8;
         let b = (self.b as i16 - other.b as i16).clamp(0, 255) as u8;
-        Color::new(r.into(), g.into(), b.into())
+        Color::new_rgba(r.into(), g.into(), b.into(), self.a.into())
     }
 }
 
@@ -82,4 +143,19 @@ impl PartialEq<u32> for Color {
     fn eq(&self, other: &u32) -> bool {
         self.to_hex() == *other
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_over_matches_float_over_on_opaque_pixels() {
+        for alpha in [0, 1, 64, 127, 128, 200, 254, 255] {
+            for value in [0, 1, 77, 128, 254, 255] {
+                let src = Color::new_rgba(value, 255 - value, value / 2, alpha);
+                let dst = 0x00_12_80_FE;
+                assert_eq!(src.over_hex(dst), src.over(Color::from_hex(dst)).to_hex());
+            }
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use crate::assets::Assets;
+use crate::color::Color;
+use crate::texture::Texture;
+
+use nalgebra_glm::Vec2;
+use std::rc::Rc;
+
+// Glifos de los niveles. Las puertas son celdas del mapa de tiles con un
+// estado aparte; las llaves se recogen al pasar por encima.
+pub const DOOR: char = 'D';
+pub const KEY_DOOR: char = 'K'; // Necesita una llave
+pub const KEYCARD_DOOR: char = 'C'; // Necesita una tarjeta
+pub const KEY: char = 'k';
+pub const KEYCARD: char = 'c';
+// Puerta abierta del todo: se puede atravesar y no bloquea la vista
+pub const OPEN_DOOR: char = '_';
+
+// Textura opcional de la salida; sin ella se tiñe la de la puerta
+pub const EXIT_TEXTURE: &str = "textures/Exit.png";
+
+// Fracción de puerta que se abre o se cierra por segundo
+const DOOR_SPEED: f32 = 1.5;
+// Segundos que una puerta queda abierta antes de cerrarse sola
+const DOOR_OPEN_TIME: f32 = 4.0;
+
+pub fn is_door(cell: char) -> bool {
+    matches!(cell, DOOR | KEY_DOOR | KEYCARD_DOOR | OPEN_DOOR)
+}
+
+// Lo que hace falta para abrir una puerta cerrada con llave
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lock {
+    Key,
+    Keycard,
+}
+
+impl Lock {
+    // Nombre del objeto en el inventario del jugador
+    pub fn item(self) -> &'static str {
+        match self {
+            Lock::Key => "Key",
+            Lock::Keycard => "Keycard",
+        }
+    }
+
+    fn for_pickup(cell: char) -> Option<Self> {
+        match cell {
+            KEY => Some(Lock::Key),
+            KEYCARD => Some(Lock::Keycard),
+            _ => None,
+        }
+    }
+}
+
+pub struct Door {
+    col: usize,
+    row: usize,
+    glyph: char, // Glifo original, para volver a él al cerrarse
+    lock: Option<Lock>,
+    open: f32, // 0.0 cerrada, 1.0 abierta
+    opening: bool,
+    open_time: f32, // Segundos abierta del todo
+}
+
+// Puertas de un nivel. El glifo de cada una en el mapa de tiles refleja si se
+// puede atravesar: `OPEN_DOOR` cuando está abierta del todo y el original en
+// cualquier otro caso, así que el resto del juego (rayos, colisiones,
+// minimapa) no necesita conocer su estado.
+pub struct Doors {
+    doors: Vec<Door>,
+}
+
+impl Doors {
+    pub fn new(maze: &[Vec<char>]) -> Self {
+        let mut doors = Vec::new();
+        for (row, line) in maze.iter().enumerate() {
+            for (col, &cell) in line.iter().enumerate() {
+                let lock = match cell {
+                    DOOR => None,
+                    KEY_DOOR => Some(Lock::Key),
+                    KEYCARD_DOOR => Some(Lock::Keycard),
+                    _ => continue,
+                };
+                doors.push(Door { col, row, glyph: cell, lock, open: 0.0, opening: false, open_time: 0.0 });
+            }
+        }
+        Doors { doors }
+    }
+
+    fn find(&self, col: usize, row: usize) -> Option<&Door> {
+        self.doors.iter().find(|door| door.col == col && door.row == row)
+    }
+
+    fn find_mut(&mut self, col: usize, row: usize) -> Option<&mut Door> {
+        self.doors.iter_mut().find(|door| door.col == col && door.row == row)
+    }
+
+    // Cuánto está abierta la puerta de la celda; 0.0 si no hay puerta
+    pub fn openness(&self, col: usize, row: usize) -> f32 {
+        self.find(col, row).map_or(0.0, |door| door.open)
+    }
+
+    // Indicación para el jugador frente a la puerta de la celda
+    pub fn prompt(&self, col: usize, row: usize, items: &[String]) -> Option<&'static str> {
+        let door = self.find(col, row)?;
+        Some(match door.lock {
+            Some(lock) if !items.iter().any(|item| item == lock.item()) => match lock {
+                Lock::Key => "Locked: you need a key",
+                Lock::Keycard => "Locked: you need a keycard",
+            },
+            Some(_) => "Press E to unlock",
+            None if door.opening => "Press E to close",
+            None => "Press E to open",
+        })
+    }
+
+    // El jugador pulsa E frente a la celda: abre o cierra la puerta, o la
+    // desbloquea si lleva lo que pide. Las puertas desbloqueadas no se vuelven
+    // a cerrar con llave.
+    pub fn interact(&mut self, col: usize, row: usize, items: &[String]) {
+        if let Some(door) = self.find_mut(col, row) {
+            match door.lock {
+                Some(lock) if items.iter().any(|item| item == lock.item()) => {
+                    door.lock = None;
+                    door.opening = true;
+                }
+                Some(_) => {}
+                None => {
+                    door.opening = !door.opening;
+                    door.open_time = 0.0;
+                }
+            }
+        }
+    }
+
+    // Un guardia quiere pasar por la celda. Los guardias llevan llaves, así
+    // que abren cualquier puerta sin desbloquearla. Devuelve si tiene que
+    // esperar a que se abra del todo.
+    pub fn request_by_guard(&mut self, col: usize, row: usize) -> bool {
+        match self.find_mut(col, row) {
+            Some(door) => {
+                door.opening = true;
+                door.open_time = 0.0;
+                door.open < 1.0
+            }
+            None => false,
+        }
+    }
+
+    // Abre del todo todas las puertas, sin desbloquearlas
+    pub fn open_all(&mut self, maze: &mut [Vec<char>]) {
+        for door in &mut self.doors {
+            door.open = 1.0;
+            door.opening = true;
+            maze[door.row][door.col] = OPEN_DOOR;
+        }
+    }
+
+    // Avanza la animación de las puertas y actualiza sus glifos en `maze`.
+    // Una puerta no se cierra mientras `occupied` indique que hay alguien en
+    // su celda.
+    pub fn update(&mut self, delta_time: f32, maze: &mut [Vec<char>], occupied: impl Fn(usize, usize) -> bool) {
+        for door in &mut self.doors {
+            if door.opening && door.open >= 1.0 {
+                door.open_time += delta_time;
+                if door.open_time >= DOOR_OPEN_TIME {
+                    door.opening = false;
+                }
+            }
+            if !door.opening && door.open > 0.0 && occupied(door.col, door.row) {
+                door.opening = true;
+                door.open_time = 0.0;
+            }
+
+            let step = if door.opening { DOOR_SPEED } else { -DOOR_SPEED } * delta_time;
+            door.open = (door.open + step).clamp(0.0, 1.0);
+            maze[door.row][door.col] = if door.open >= 1.0 { OPEN_DOOR } else { door.glyph };
+        }
+    }
+}
+
+// Llave o tarjeta tirada en el suelo
+pub struct Pickup {
+    pub pos: Vec2,
+    pub lock: Lock,
+}
+
+// Llaves del nivel, en el centro de su celda
+pub fn find_pickups(level: &[Vec<char>], block_size: f32) -> Vec<Pickup> {
+    let mut pickups = Vec::new();
+    for (row, line) in level.iter().enumerate() {
+        for (col, &cell) in line.iter().enumerate() {
+            if let Some(lock) = Lock::for_pickup(cell) {
+                let pos = Vec2::new((col as f32 + 0.5) * block_size, (row as f32 + 0.5) * block_size);
+                pickups.push(Pickup { pos, lock });
+            }
+        }
+    }
+    pickups
+}
+
+// Recoge las llaves de la celda del jugador y las añade a `items`. Devuelve
+// si se recogió alguna.
+pub fn collect_pickups(pickups: &mut Vec<Pickup>, player_pos: Vec2, block_size: f32, items: &mut Vec<String>) -> bool {
+    let cell = |pos: Vec2| ((pos.x / block_size) as usize, (pos.y / block_size) as usize);
+    let before = pickups.len();
+    pickups.retain(|pickup| {
+        if cell(pickup.pos) == cell(player_pos) {
+            items.push(pickup.lock.item().to_string());
+            false
+        } else {
+            true
+        }
+    });
+    pickups.len() != before
+}
+
+// Sprite de una llave o tarjeta: pequeño y abajo en el centro de un lienzo
+// transparente, para que quede en el suelo al dibujarlo como a los guardias
+pub fn pickup_texture(lock: Lock) -> Texture {
+    const SIZE: usize = 64;
+    let mut data = vec![Color::transparent(); SIZE * SIZE];
+    let mut fill = |x0: usize, y0: usize, width: usize, height: usize, color: Color| {
+        for y in y0..(y0 + height).min(SIZE) {
+            for x in x0..(x0 + width).min(SIZE) {
+                data[y * SIZE + x] = color;
+            }
+        }
+    };
+
+    match lock {
+        Lock::Key => {
+            let gold = Color::new(230, 190, 60);
+            // Anilla, caña y dientes
+            fill(27, 40, 10, 3, gold);
+            fill(27, 47, 10, 3, gold);
+            fill(27, 40, 3, 10, gold);
+            fill(34, 40, 3, 10, gold);
+            fill(31, 50, 3, 13, gold);
+            fill(34, 56, 4, 2, gold);
+            fill(34, 60, 4, 2, gold);
+        }
+        Lock::Keycard => {
+            fill(22, 48, 20, 14, Color::new(60, 140, 230));
+            fill(22, 51, 20, 3, Color::new(20, 20, 30));
+            fill(25, 56, 8, 4, Color::new(240, 240, 240));
+        }
+    }
+    Texture::new(SIZE, SIZE, data)
+}
+
+// Textura de la salida: la de la puerta teñida de verde con una franja
+// iluminada arriba, como un letrero
+pub fn exit_texture(door: &Texture) -> Texture {
+    let sign = Color::new(60, 230, 110);
+    let data = (0..door.width * door.height)
+        .map(|i| {
+            let color = door.get_color(i % door.width, i / door.width);
+            if i / door.width < door.height / 8 {
+                sign
+            } else {
+                Color::new(20, 150, 60).blend(color, 0.55)
+            }
+        })
+        .collect();
+    Texture::new(door.width, door.height, data)
+}
+
+// La textura de la salida si está en los recursos o la generada a partir de
+// la de la puerta
+pub fn load_exit_texture(assets: &mut Assets, door: &Texture) -> Rc<Texture> {
+    if assets.exists(EXIT_TEXTURE) {
+        assets.texture(EXIT_TEXTURE)
+    } else {
+        Rc::new(exit_texture(door))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(rows: &[&str]) -> Vec<Vec<char>> {
+        rows.iter().map(|row| row.chars().collect()).collect()
+    }
+
+    fn run(doors: &mut Doors, maze: &mut [Vec<char>], seconds: f32, occupied: bool) {
+        for _ in 0..(seconds * 30.0) as usize {
+            doors.update(1.0 / 30.0, maze, |_, _| occupied);
+        }
+    }
+
+    #[test]
+    fn doors_slide_open_and_close_by_themselves() {
+        let mut maze = level(&["+-D-+"]);
+        let mut doors = Doors::new(&maze);
+        assert_eq!(doors.prompt(2, 0, &[]), Some("Press E to open"));
+
+        doors.interact(2, 0, &[]);
+        run(&mut doors, &mut maze, 0.3, false);
+        let half = doors.openness(2, 0);
+        assert!(half > 0.0 && half < 1.0);
+        // A medio abrir sigue bloqueando
+        assert_eq!(maze[0][2], DOOR);
+
+        run(&mut doors, &mut maze, 0.5, false);
+        assert_eq!(maze[0][2], OPEN_DOOR);
+        assert_eq!(doors.prompt(2, 0, &[]), Some("Press E to close"));
+
+        // Se cierra sola, pero no con alguien dentro
+        run(&mut doors, &mut maze, DOOR_OPEN_TIME + 1.0, true);
+        assert_eq!(maze[0][2], OPEN_DOOR);
+        run(&mut doors, &mut maze, DOOR_OPEN_TIME + 1.0, false);
+        assert_eq!(maze[0][2], DOOR);
+        assert_eq!(doors.openness(2, 0), 0.0);
+    }
+
+    #[test]
+    fn locked_doors_need_their_item() {
+        let mut maze = level(&["K C"]);
+        let mut doors = Doors::new(&maze);
+        let key = vec!["Key".to_string()];
+
+        doors.interact(0, 0, &[]);
+        run(&mut doors, &mut maze, 1.0, false);
+        assert_eq!(doors.openness(0, 0), 0.0);
+        assert_eq!(doors.prompt(0, 0, &[]), Some("Locked: you need a key"));
+
+        assert_eq!(doors.prompt(0, 0, &key), Some("Press E to unlock"));
+        doors.interact(0, 0, &key);
+        run(&mut doors, &mut maze, 1.0, false);
+        assert_eq!(maze[0][0], OPEN_DOOR);
+        assert_eq!(doors.prompt(0, 0, &[]), Some("Press E to close"));
+
+        // La llave no abre la puerta de tarjeta
+        doors.interact(2, 0, &key);
+        assert_eq!(doors.prompt(2, 0, &key), Some("Locked: you need a keycard"));
+    }
+
+    #[test]
+    fn guards_open_any_door_and_wait_for_it() {
+        let mut maze = level(&["CD"]);
+        let mut doors = Doors::new(&maze);
+
+        assert!(doors.request_by_guard(0, 0));
+        run(&mut doors, &mut maze, 1.0, false);
+        assert!(!doors.request_by_guard(0, 0));
+        assert_eq!(maze[0][0], OPEN_DOOR);
+        // Sigue cerrada con llave para el jugador
+        assert_eq!(doors.prompt(0, 0, &[]), Some("Locked: you need a keycard"));
+
+        // Sin puerta no hay que esperar
+        assert!(!doors.request_by_guard(5, 5));
+    }
+
+    #[test]
+    fn keys_are_collected_from_the_player_cell() {
+        let maze = level(&["  k", " c "]);
+        let mut pickups = find_pickups(&maze, 10.0);
+        let mut items = Vec::new();
+        assert_eq!(pickups.len(), 2);
+
+        assert!(!collect_pickups(&mut pickups, Vec2::new(5.0, 5.0), 10.0, &mut items));
+        assert!(collect_pickups(&mut pickups, Vec2::new(28.0, 2.0), 10.0, &mut items));
+        assert_eq!(items, ["Key"]);
+        assert_eq!(pickups.len(), 1);
+    }
+}
@@ -1,13 +1,16 @@
 use nalgebra_glm::Vec2;
 use std::f32::consts::PI;
+use crate::occupancy::Occupancy;
+use crate::cast_ray::cast_ray;
+use crate::maze::is_wall;
 
 #[derive(Clone)]
 pub struct Enemy {
     pos: Vec2,
     a: f32,
     speed: f32,
-    fov_angle: f32,
-    fov_range: f32
+    fov_angle: f32, // Apertura del cono de visión en grados
+    fov_range: f32  // Alcance del cono de visión en unidades del mundo
 }
 
 impl Enemy {
@@ -23,6 +26,20 @@ impl Enemy {
         self.a
     }
 
+    // Dirección real de movimiento: con velocidad negativa el guardia avanza
+    // en sentido contrario a su ángulo
+    pub fn heading(&self) -> f32 {
+        if self.speed < 0.0 {
+            self.a + PI
+        } else {
+            self.a
+        }
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.speed != 0.0
+    }
+
     pub fn get_fov_angle(&self) -> f32 {
         self.fov_angle
     }
@@ -31,11 +48,40 @@ impl Enemy {
         self.fov_range
     }
 
+    // Si `target` está dentro del cono de visión y sin paredes de por medio
+    pub fn can_see(&self, target: Vec2, maze: &Vec<Vec<char>>, block_size: f32) -> bool {
+        let offset = target - self.pos;
+        let distance = offset.norm();
+        if distance > self.fov_range {
+            return false;
+        }
+
+        let angle = offset.y.atan2(offset.x);
+        let difference = (angle - self.heading() + PI).rem_euclid(2.0 * PI) - PI;
+        if difference.abs() > self.fov_angle.to_radians() / 2.0 {
+            return false;
+        }
+
+        match cast_ray(&self.pos, angle, maze, block_size, false, distance, None) {
+            Some(hit) => hit.distance >= distance,
+            None => true,
+        }
+    }
+
+    // Celda a `distance` por delante en la dirección en que avanza
+    pub fn cell_ahead(&self, distance: f32, block_size: f32) -> Option<(usize, usize)> {
+        let ahead = self.pos + Vec2::new(self.heading().cos(), self.heading().sin()) * distance;
+        if ahead.x < 0.0 || ahead.y < 0.0 {
+            return None;
+        }
+        Some(((ahead.x / block_size) as usize, (ahead.y / block_size) as usize))
+    }
+
     pub fn set_pos(&mut self, new_pos: Vec2) {
         self.pos = new_pos;
     }
 
-    pub fn update(&mut self, delta_time: f32, maze: &Vec<Vec<char>>, block_size: f32) -> bool {
+    pub fn update(&mut self, delta_time: f32, maze: &Vec<Vec<char>>, block_size: f32, occupancy: &Occupancy) -> bool {
         let new_pos = Vec2::new(
             self.pos.x + self.a.cos() * self.speed * delta_time,
             self.pos.y + self.a.sin() * self.speed * delta_time,
@@ -55,7 +101,7 @@ impl Enemy {
             self.pos = new_pos;
         }
 
-        if temp_enemy.check_collision_with_player(maze, block_size) {
+        if temp_enemy.check_collision_with_player(maze, occupancy) {
             return true;
         } else {
             return false;
@@ -66,23 +112,24 @@ impl Enemy {
         let maze_x = (self.pos.x / block_size) as usize;
         let maze_y = (self.pos.y / block_size) as usize;
 
-        maze[maze_y][maze_x] == '+' || maze[maze_y][maze_x] == '|' || maze[maze_y][maze_x] == '-' || maze[maze_y][maze_x] == '/' || maze[maze_y][maze_x] == '!'
+        is_wall(maze, maze_x, maze_y).0
     }
 
-    pub fn check_collision_with_player(&self, maze: &Vec<Vec<char>>, block_size: f32) -> bool {
+    pub fn check_collision_with_player(&self, maze: &Vec<Vec<char>>, occupancy: &Occupancy) -> bool {
         let cos_a = self.a.cos().round();
         let sin_a = self.a.sin().round();
     
-        let maze_x = (self.pos.x / block_size).floor() as isize;
-        let maze_y = (self.pos.y / block_size).floor() as isize;
+        let (maze_x, maze_y) = occupancy.cell_of(self.pos);
+        let maze_x = maze_x as isize;
+        let maze_y = maze_y as isize;
     
         let lookahead_x = maze_x - cos_a as isize;
         let lookahead_y = maze_y - sin_a as isize;
     
         // Verifica que los índices estén dentro de los límites
-        if maze_y >= 0 && maze_x >= 0 && (maze_y as usize) < maze.len() && (maze_x as usize) < maze[0].len() {    
+        if (maze_y as usize) < maze.len() && (maze_x as usize) < maze[0].len() {    
             if lookahead_y >= 0 && lookahead_x >= 0 && (lookahead_y as usize) < maze.len() && (lookahead_x as usize) < maze[0].len() {    
-                return maze[maze_y as usize][maze_x as usize] == 'p' || maze[lookahead_y as usize][lookahead_x as usize] == 'p';
+                return occupancy.has_player(maze_x as usize, maze_y as usize) || occupancy.has_player(lookahead_x as usize, lookahead_y as usize);
             }
         }
     
@@ -90,4 +137,24 @@ impl Enemy {
     }
     
     
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sight_needs_range_angle_and_a_clear_line() {
+        let maze: Vec<Vec<char>> = ["+-----+", "|     |", "|  |  |", "+-----+"].iter().map(|row| row.chars().collect()).collect();
+        // Guardia en la celda (1, 1) mirando hacia la derecha
+        let guard = Enemy::new(Vec2::new(15.0, 15.0), 0.0, 55.0, 60.0, 50.0);
+
+        assert!(guard.can_see(Vec2::new(45.0, 15.0), &maze, 10.0));
+        // Detrás del guardia
+        assert!(!guard.can_see(Vec2::new(11.0, 15.0), &maze, 10.0));
+        // Fuera de alcance
+        assert!(!guard.can_see(Vec2::new(75.0, 15.0), &maze, 10.0));
+        // Tras la pared de la celda (3, 2)
+        assert!(!guard.can_see(Vec2::new(45.0, 25.0), &maze, 10.0));
+    }
+}
@@ -0,0 +1,77 @@
+use nalgebra_glm::Vec2;
+
+// Memoria de exploración de un nivel: celdas vistas por el jugador y la última
+// posición conocida de cada guardia. Se crea de nuevo al empezar cada nivel.
+pub struct Exploration {
+    cols: usize,
+    rows: usize,
+    explored: Vec<bool>,
+    last_seen: Vec<Option<Vec2>>,
+    revision: u64, // Aumenta cada vez que se descubren celdas nuevas
+}
+
+impl Exploration {
+    pub fn new(maze: &Vec<Vec<char>>, enemy_count: usize) -> Self {
+        let rows = maze.len();
+        let cols = maze.iter().map(|row| row.len()).max().unwrap_or(0);
+
+        Exploration {
+            cols,
+            rows,
+            explored: vec![false; cols * rows],
+            last_seen: vec![None; enemy_count],
+            revision: 0,
+        }
+    }
+
+    // Marca como exploradas las celdas dentro de `radius` alrededor de (col, row)
+    pub fn reveal_around(&mut self, col: usize, row: usize, radius: usize) {
+        let min_row = row.saturating_sub(radius);
+        let max_row = (row + radius + 1).min(self.rows);
+        let min_col = col.saturating_sub(radius);
+        let max_col = (col + radius + 1).min(self.cols);
+
+        for r in min_row..max_row {
+            for c in min_col..max_col {
+                let cell = &mut self.explored[r * self.cols + c];
+                if !*cell {
+                    *cell = true;
+                    self.revision += 1;
+                }
+            }
+        }
+    }
+
+    pub fn is_explored(&self, col: usize, row: usize) -> bool {
+        col < self.cols && row < self.rows && self.explored[row * self.cols + col]
+    }
+
+    // Cambia solo cuando cambian las celdas exploradas; sirve para saber si
+    // hay que redibujar el minimapa
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn see_guard(&mut self, index: usize, pos: Vec2) {
+        if let Some(slot) = self.last_seen.get_mut(index) {
+            *slot = Some(pos);
+        }
+    }
+
+    pub fn last_seen_guards(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.last_seen.iter().flatten().copied()
+    }
+
+    // Salidas ('/') que el jugador ya ha descubierto
+    pub fn known_exits(&self, maze: &Vec<Vec<char>>) -> Vec<(usize, usize)> {
+        let mut exits = Vec::new();
+        for (row, line) in maze.iter().enumerate() {
+            for (col, cell) in line.iter().enumerate() {
+                if *cell == '/' && self.is_explored(col, row) {
+                    exits.push((col, row));
+                }
+            }
+        }
+        exits
+    }
+}
@@ -1,27 +1,22 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+// Línea que separa el laberinto de la capa de suelo en un archivo de nivel
+pub const FLOOR_SECTION: &str = "[floor]";
 
-pub fn load_maze(filename: &str) -> Vec<Vec<char>> {
-    let file = match File::open(filename) {
-        Ok(file) => file,
-        Err(e) => {
-            eprintln!("Error opening file {}: {}", filename, e);
-            return vec![]; // Retorna un laberinto vacío en caso de error
-        }
-    };
-
-    let reader = BufReader::new(file);
-
-    let maze: Vec<Vec<char>> = reader
+// Convierte el contenido de un archivo de laberinto en una cuadrícula de caracteres
+pub fn parse_maze(contents: &str) -> Vec<Vec<char>> {
+    contents
         .lines()
-        .map(|line| match line {
-            Ok(line) => line.chars().collect(),
-            Err(e) => {
-                eprintln!("Error reading line: {}", e);
-                vec![] // En caso de error en la lectura de una línea, devuelve una fila vacía
-            }
-        })
-        .collect();
+        .take_while(|line| line.trim() != FLOOR_SECTION)
+        .map(|line| line.chars().collect())
+        .collect()
+}
 
-    maze
-}
\ No newline at end of file
+// Capa opcional de tipos de suelo que sigue a `[floor]`, alineada celda a
+// celda con el laberinto. Vacía si el nivel no la define.
+pub fn parse_floor(contents: &str) -> Vec<Vec<char>> {
+    contents
+        .lines()
+        .skip_while(|line| line.trim() != FLOOR_SECTION)
+        .skip(1)
+        .map(|line| line.chars().collect())
+        .collect()
+}
@@ -0,0 +1,237 @@
+use crate::assets::{Assets, Sound};
+use crate::audio_engine::{AudioEngine, Bus};
+use crate::rng::next_random;
+
+use nalgebra_glm::Vec2;
+use std::rc::Rc;
+
+// Distancia recorrida entre dos pasos, en unidades del mundo. Al correr la
+// zancada es más larga pero no tanto como la velocidad, así que los pasos
+// suenan más seguidos.
+const WALK_STRIDE: f32 = 35.0;
+const SPRINT_STRIDE: f32 = 45.0;
+
+const WALK_VOLUME: f32 = 0.1;
+const SPRINT_VOLUME: f32 = 0.16;
+
+// Variación aleatoria de tono y volumen entre pasos
+const PITCH_JITTER: f32 = 0.06;
+const VOLUME_JITTER: f32 = 0.15;
+
+// Tipo de suelo de una celda
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Surface {
+    Concrete,
+    Metal,
+    Water,
+    Gravel,
+}
+
+const SURFACES: [Surface; 4] = [Surface::Concrete, Surface::Metal, Surface::Water, Surface::Gravel];
+
+impl Surface {
+    // Leyenda de la sección `[floor]`: `m` metal, `w` agua, `g` grava; cualquier
+    // otro carácter es cemento
+    fn from_char(c: char) -> Self {
+        match c {
+            'm' => Surface::Metal,
+            'w' => Surface::Water,
+            'g' => Surface::Gravel,
+            _ => Surface::Concrete,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Surface::Concrete => 0,
+            Surface::Metal => 1,
+            Surface::Water => 2,
+            Surface::Gravel => 3,
+        }
+    }
+
+    // Muestras de cada suelo; se elige una distinta de la anterior en cada paso.
+    // Salvo `Footsteps.wav`, son provisionales: ruido sintético de la misma
+    // duración, hasta tener grabaciones reales. Un mod puede sustituirlas por
+    // grabaciones propias con estos nombres.
+    fn samples(self) -> &'static [&'static str] {
+        match self {
+            Surface::Concrete => &["Audio/Footsteps.wav", "Audio/Footsteps_concrete2.wav"],
+            Surface::Metal => &["Audio/Footsteps_metal.wav", "Audio/Footsteps_metal2.wav"],
+            Surface::Water => &["Audio/Footsteps_water.wav", "Audio/Footsteps_water2.wav"],
+            Surface::Gravel => &["Audio/Footsteps_gravel.wav", "Audio/Footsteps_gravel2.wav"],
+        }
+    }
+
+    // Tono y volumen base con los que se reproduce la muestra genérica cuando
+    // el suelo no tiene grabaciones propias
+    fn pitch(self) -> f32 {
+        match self {
+            Surface::Concrete => 1.0,
+            Surface::Metal => 1.25,
+            Surface::Water => 0.8,
+            Surface::Gravel => 0.9,
+        }
+    }
+
+    fn volume(self) -> f32 {
+        match self {
+            Surface::Concrete => 1.0,
+            Surface::Metal => 1.4,
+            Surface::Water => 1.2,
+            Surface::Gravel => 1.1,
+        }
+    }
+}
+
+// Tipos de suelo de un nivel
+pub struct FloorMap {
+    cells: Vec<Vec<Surface>>,
+}
+
+impl FloorMap {
+    pub fn new(floor: &Vec<Vec<char>>) -> Self {
+        FloorMap {
+            cells: floor.iter().map(|row| row.iter().map(|&c| Surface::from_char(c)).collect()).collect(),
+        }
+    }
+
+    // Suelo bajo `pos`; cemento fuera de la capa definida
+    pub fn surface_at(&self, pos: Vec2, block_size: f32) -> Surface {
+        if pos.x < 0.0 || pos.y < 0.0 {
+            return Surface::Concrete;
+        }
+        let col = (pos.x / block_size) as usize;
+        let row = (pos.y / block_size) as usize;
+        self.cells
+            .get(row)
+            .and_then(|cells| cells.get(col))
+            .copied()
+            .unwrap_or(Surface::Concrete)
+    }
+}
+
+// Pasos del jugador: suenan por distancia recorrida, no por tecla pulsada,
+// así que no hay pasos contra una pared
+pub struct Footsteps {
+    sets: Vec<Vec<Rc<Sound>>>,
+    travelled: f32,
+    last_sample: Option<usize>,
+    seed: u32,
+}
+
+impl Footsteps {
+    // Carga las muestras de cada suelo. Las que no existen se descartan y el
+    // suelo usa la muestra genérica con su propio tono.
+    pub fn new(assets: &mut Assets) -> Self {
+        let generic = assets.sound(Surface::Concrete.samples()[0]);
+
+        let sets = SURFACES
+            .iter()
+            .map(|surface| {
+                let mut set = Vec::new();
+                for path in surface.samples() {
+                    if assets.exists(path) {
+                        set.push(assets.sound(path));
+                    }
+                }
+                if set.is_empty() {
+                    set.push(Rc::new(generic.with_speed(surface.pitch())));
+                }
+                set
+            })
+            .collect();
+
+        Footsteps::with_sets(sets)
+    }
+
+    fn with_sets(sets: Vec<Vec<Rc<Sound>>>) -> Self {
+        Footsteps {
+            sets,
+            travelled: 0.0,
+            last_sample: None,
+            seed: 0x2545_F491,
+        }
+    }
+
+    // `distance` es lo que el jugador se movió realmente este frame
+    pub fn update(&mut self, distance: f32, sprinting: bool, surface: Surface, audio: &mut AudioEngine) {
+        if distance <= 0.0 {
+            return;
+        }
+
+        let stride = if sprinting { SPRINT_STRIDE } else { WALK_STRIDE };
+        self.travelled += distance;
+        if self.travelled < stride {
+            return;
+        }
+        self.travelled %= stride;
+
+        let set = &self.sets[surface.index()];
+        let mut sample = (next_random(&mut self.seed) * set.len() as f32) as usize % set.len();
+        if set.len() > 1 && Some(sample) == self.last_sample {
+            sample = (sample + 1) % set.len();
+        }
+        self.last_sample = Some(sample);
+
+        let pitch = 1.0 + PITCH_JITTER * (2.0 * next_random(&mut self.seed) - 1.0);
+        let base = if sprinting { SPRINT_VOLUME } else { WALK_VOLUME };
+        let volume = base * surface.volume() * (1.0 + VOLUME_JITTER * (2.0 * next_random(&mut self.seed) - 1.0));
+
+        audio.play(&set[sample].with_speed(pitch), Bus::Sfx, volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset_pack::AssetPack;
+    use crate::audio_backend::NullBackend;
+
+    #[test]
+    fn floor_map_reads_surfaces_per_cell() {
+        let floor = FloorMap::new(&vec![vec![' ', 'm'], vec!['w', 'g']]);
+
+        assert_eq!(floor.surface_at(Vec2::new(150.0, 50.0), 100.0), Surface::Metal);
+        assert_eq!(floor.surface_at(Vec2::new(50.0, 150.0), 100.0), Surface::Water);
+        assert_eq!(floor.surface_at(Vec2::new(950.0, 50.0), 100.0), Surface::Concrete);
+    }
+
+    #[test]
+    fn steps_follow_distance_and_cadence() {
+        let backend = NullBackend::new();
+        let log = backend.log();
+        let mut audio = AudioEngine::new(Box::new(backend));
+        let sets = SURFACES.iter().map(|_| vec![Rc::new(Sound::silence("Audio/Footsteps.wav"))]).collect();
+        let mut footsteps = Footsteps::with_sets(sets);
+
+        // Contra una pared no hay movimiento y no hay pasos
+        for _ in 0..100 {
+            footsteps.update(0.0, false, Surface::Concrete, &mut audio);
+        }
+        assert_eq!(log.count_played("Audio/Footsteps.wav"), 0);
+
+        // 100 frames caminando a 2.3 y otros 100 corriendo a 3.68
+        for _ in 0..100 {
+            footsteps.update(2.3, false, Surface::Concrete, &mut audio);
+        }
+        let walking = log.count_played("Audio/Footsteps.wav");
+        for _ in 0..100 {
+            footsteps.update(3.68, true, Surface::Concrete, &mut audio);
+        }
+        let sprinting = log.count_played("Audio/Footsteps.wav") - walking;
+
+        assert_eq!(walking, (100.0 * 2.3 / WALK_STRIDE) as usize);
+        assert!(sprinting > walking);
+    }
+
+    #[test]
+    fn every_surface_has_its_own_embedded_samples() {
+        let footsteps = Footsteps::new(&mut Assets::new(AssetPack::new(None)));
+
+        for surface in SURFACES {
+            let names: Vec<&str> = footsteps.sets[surface.index()].iter().map(|sound| sound.name()).collect();
+            assert_eq!(names, surface.samples(), "{:?}", surface);
+        }
+    }
+}
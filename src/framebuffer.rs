@@ -1,9 +1,90 @@
-use crate::bmp::write_bmp_file;
+use crate::bmp::{write_bmp_file, BmpFormat};
 use crate::color::Color;
-use image::{GenericImageView, Pixel, Rgba};
-use image::imageops::FilterType;
+use crate::texture::Texture;
 
-use rusttype::{Font, Scale, point, PositionedGlyph};
+use minifb::Window;
+use rusttype::{Font, Scale, point};
+
+// Cómo se lleva la resolución interna al tamaño de la ventana
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scaling {
+    // Solo múltiplos enteros: píxeles nítidos y bandas negras más anchas
+    Integer,
+    // Ocupa todo lo posible manteniendo la relación de aspecto
+    Fractional,
+}
+
+// Zona de la ventana donde se dibuja la imagen escalada: (x, y, ancho, alto)
+fn viewport(width: usize, height: usize, window_width: usize, window_height: usize, scaling: Scaling) -> (usize, usize, usize, usize) {
+    let fit = (window_width as f32 / width as f32).min(window_height as f32 / height as f32);
+    // Si la ventana es más pequeña que la resolución interna no hay escala
+    // entera posible y se reduce de forma fraccionaria
+    let scale = match scaling {
+        Scaling::Integer if fit >= 1.0 => fit.floor(),
+        _ => fit,
+    };
+
+    let view_width = ((width as f32 * scale).round() as usize).clamp(1, window_width);
+    let view_height = ((height as f32 * scale).round() as usize).clamp(1, window_height);
+    ((window_width - view_width) / 2, (window_height - view_height) / 2, view_width, view_height)
+}
+
+// Escala `src` al centro de `dst` por vecino más cercano, con bandas negras
+fn scale_into(src: &[u32], width: usize, height: usize, dst: &mut [u32], window_width: usize, window_height: usize, scaling: Scaling) {
+    let view = viewport(width, height, window_width, window_height, scaling);
+    dst.fill(0);
+    stretch_into(src, width, height, dst, window_width, view);
+}
+
+// Copia `src` estirada por vecino más cercano al rectángulo `view` de `dst`
+fn stretch_into(src: &[u32], width: usize, height: usize, dst: &mut [u32], dst_width: usize, view: (usize, usize, usize, usize)) {
+    let (view_x, view_y, view_width, view_height) = view;
+    let columns: Vec<usize> = (0..view_width).map(|x| x * width / view_width).collect();
+    for y in 0..view_height {
+        let src_row = &src[(y * height / view_height) * width..][..width];
+        let dst_row = &mut dst[(view_y + y) * dst_width + view_x..][..view_width];
+        for (pixel, &column) in dst_row.iter_mut().zip(&columns) {
+            *pixel = src_row[column];
+        }
+    }
+}
+
+// Franja de filas consecutivas del framebuffer. Las franjas no se solapan, así
+// que cada una se puede dibujar desde un hilo distinto.
+pub struct RowBand<'a> {
+    top: usize,
+    width: usize,
+    pixels: &'a mut [u32],
+}
+
+impl<'a> RowBand<'a> {
+    // Primera fila (absoluta) de la franja
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    pub fn rows(&self) -> usize {
+        self.pixels.len() / self.width.max(1)
+    }
+
+    // Fila `y` (absoluta) de la franja
+    pub fn row_mut(&mut self, y: usize) -> &mut [u32] {
+        let start = (y - self.top) * self.width;
+        &mut self.pixels[start..start + self.width]
+    }
+
+    // Columna de textura en `x` para las filas `top..bottom` que caen dentro
+    // de la franja; la fila `y` usa el texel (`texture_x`, (y - top) * `texture_y_step`)
+    pub fn vline_textured(&mut self, x: usize, top: usize, bottom: usize, texture: &Texture, texture_x: usize, texture_y_step: f32) {
+        if x >= self.width {
+            return;
+        }
+        for y in top.max(self.top)..bottom.min(self.top + self.rows()) {
+            let texture_y = ((y as f32 - top as f32) * texture_y_step) as usize;
+            self.pixels[(y - self.top) * self.width + x] = texture.get_color(texture_x, texture_y).to_hex();
+        }
+    }
+}
 
 pub struct Framebuffer {
     width: usize,
@@ -11,6 +92,9 @@ pub struct Framebuffer {
     buffer: Vec<u32>,
     background_color: Color,
     current_color: Color,
+    scaling: Scaling,
+    output: Vec<u32>, // Imagen escalada al tamaño de la ventana
+    transparent: bool, // Capa con alfa (ARGB) para componer encima de otra
 }
 
 impl Framebuffer {
@@ -26,7 +110,83 @@ impl Framebuffer {
             buffer,
             background_color,
             current_color,
+            scaling: Scaling::Integer,
+            output: Vec::new(),
+            transparent: false,
+        }
+    }
+
+    // Capa fuera de pantalla que empieza transparente. Guarda el alfa de cada
+    // píxel y se dibuja sobre otro framebuffer con `composite`.
+    pub fn new_transparent(width: usize, height: usize) -> Self {
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.transparent = true;
+        framebuffer.background_color = Color::transparent();
+        framebuffer.clear();
+        framebuffer
+    }
+
+    // Valor de `color` en el formato del buffer
+    fn encode(&self, color: Color) -> u32 {
+        if self.transparent { color.to_argb() } else { color.to_hex() }
+    }
+
+    fn decode(&self, pixel: u32) -> Color {
+        if self.transparent { Color::from_argb(pixel) } else { Color::from_hex(pixel) }
+    }
+
+    // Mezcla `color` sobre el píxel `dst` usando su alfa
+    fn blend_pixel(&self, color: Color, dst: u32) -> u32 {
+        if self.transparent {
+            color.over(Color::from_argb(dst)).to_argb()
+        } else {
+            color.over_hex(dst)
+        }
+    }
+
+    pub fn set_scaling(&mut self, scaling: Scaling) {
+        self.scaling = scaling;
+    }
+
+    // Convierte una posición en píxeles de una ventana de `window_width` x
+    // `window_height` a píxeles del framebuffer, deshaciendo la escala y las
+    // bandas negras de `present`. Fuera de la imagen da valores fuera de rango.
+    pub fn window_to_framebuffer(&self, x: f32, y: f32, window_width: usize, window_height: usize) -> (f32, f32) {
+        if window_width == 0 || window_height == 0 {
+            return (x, y);
         }
+        let (view_x, view_y, view_width, view_height) = viewport(self.width, self.height, window_width, window_height, self.scaling);
+        (
+            (x - view_x as f32) * self.width as f32 / view_width as f32,
+            (y - view_y as f32) * self.height as f32 / view_height as f32,
+        )
+    }
+
+    // Posición del ratón en píxeles del framebuffer
+    pub fn mouse_pos(&self, window: &Window) -> Option<(f32, f32)> {
+        let (x, y) = window.get_mouse_pos(minifb::MouseMode::Clamp)?;
+        let (window_width, window_height) = window.get_size();
+        Some(self.window_to_framebuffer(x, y, window_width, window_height))
+    }
+
+    // Muestra el frame en la ventana escalándolo a su tamaño actual, así que
+    // la ventana se puede redimensionar sin cambiar la resolución interna
+    pub fn present(&mut self, window: &mut Window) {
+        let (window_width, window_height) = window.get_size();
+        if window_width == 0 || window_height == 0 {
+            // Ventana minimizada: solo se procesan los eventos
+            window.update();
+            return;
+        }
+
+        if (window_width, window_height) == (self.width, self.height) {
+            window.update_with_buffer(&self.buffer, self.width, self.height).unwrap();
+            return;
+        }
+
+        self.output.resize(window_width * window_height, 0);
+        scale_into(&self.buffer, self.width, self.height, &mut self.output, window_width, window_height, self.scaling);
+        window.update_with_buffer(&self.output, window_width, window_height).unwrap();
     }
 
     pub fn get_width(&self) -> usize {
@@ -42,14 +202,26 @@ impl Framebuffer {
     }
 
     pub fn clear(&mut self) {
-        let color_hex = self.background_color.to_hex();
+        let color_hex = self.encode(self.background_color);
         self.buffer.fill(color_hex);
     }
 
     pub fn point(&mut self, x: isize, y: isize) {
         if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
             let index = (self.width * y as usize) + x as usize;
-            self.buffer[index] = self.current_color.to_hex();
+            self.buffer[index] = self.encode(self.current_color);
+        }
+    }
+
+    // Mezcla `color` sobre el píxel existente usando su canal alfa
+    pub fn blend_point(&mut self, x: isize, y: isize, color: Color) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            let index = (self.width * y as usize) + x as usize;
+            if color.is_opaque() {
+                self.buffer[index] = self.encode(color);
+            } else if color.alpha() > 0 {
+                self.buffer[index] = self.blend_pixel(color, self.buffer[index]);
+            }
         }
     }
 
@@ -70,11 +242,125 @@ impl Framebuffer {
         }
     }
 
+    // Las capas transparentes guardan el alfa; el resto, solo el color
     pub fn save_as_bmp(&self, file_path: &str) -> std::io::Result<()> {
-        write_bmp_file(file_path, &self.buffer, self.width, self.height)
+        let format = if self.transparent { BmpFormat::Argb32 } else { BmpFormat::Rgb24 };
+        write_bmp_file(file_path, &self.buffer, self.width, self.height, format)
+    }
+
+    // Recorta el rectángulo a la pantalla. Devuelve las esquinas (x0, y0) y
+    // (x1, y1), esta última exclusiva, o `None` si no queda nada visible.
+    fn clip_rect(&self, x: isize, y: isize, width: usize, height: usize) -> Option<(usize, usize, usize, usize)> {
+        let x0 = x.max(0);
+        let y0 = y.max(0);
+        let x1 = (x + width as isize).min(self.width as isize);
+        let y1 = (y + height as isize).min(self.height as isize);
+        if x0 >= x1 || y0 >= y1 {
+            None
+        } else {
+            Some((x0 as usize, y0 as usize, x1 as usize, y1 as usize))
+        }
+    }
+
+    // Rellena un rectángulo con un color opaco
+    pub fn fill_rect(&mut self, x: isize, y: isize, width: usize, height: usize, color: Color) {
+        if let Some((x0, y0, x1, y1)) = self.clip_rect(x, y, width, height) {
+            let hex = self.encode(color);
+            for row in y0..y1 {
+                self.buffer[row * self.width + x0..row * self.width + x1].fill(hex);
+            }
+        }
+    }
+
+    // Mezcla un rectángulo usando el alfa de `color`
+    pub fn blend_rect(&mut self, x: isize, y: isize, width: usize, height: usize, color: Color) {
+        if color.is_opaque() {
+            self.fill_rect(x, y, width, height, color);
+            return;
+        }
+        if color.alpha() == 0 {
+            return;
+        }
+        if let Some((x0, y0, x1, y1)) = self.clip_rect(x, y, width, height) {
+            for row in y0..y1 {
+                for index in row * self.width + x0..row * self.width + x1 {
+                    self.buffer[index] = self.blend_pixel(color, self.buffer[index]);
+                }
+            }
+        }
+    }
+
+    // Línea horizontal de `x0` a `x1`, ambos incluidos
+    pub fn hline(&mut self, x0: isize, x1: isize, y: isize, color: Color) {
+        let (left, right) = if x0 <= x1 { (x0, x1) } else { (x1, x0) };
+        self.fill_rect(left, y, (right - left + 1) as usize, 1, color);
+    }
+
+    // Dibuja `texture` estirada al rectángulo indicado (vecino más cercano),
+    // mezclando cada texel por su alfa
+    pub fn blit_texture_scaled(&mut self, texture: &Texture, x: isize, y: isize, width: usize, height: usize) {
+        let (x0, y0, x1, y1) = match self.clip_rect(x, y, width, height) {
+            Some(rect) => rect,
+            None => return,
+        };
+
+        let columns: Vec<usize> = (x0..x1).map(|dx| (dx as isize - x) as usize * texture.width / width).collect();
+        for dy in y0..y1 {
+            let texture_y = (dy as isize - y) as usize * texture.height / height;
+            let row_start = dy * self.width + x0;
+            for (index, &texture_x) in (row_start..row_start + columns.len()).zip(&columns) {
+                let color = texture.get_color(texture_x, texture_y);
+                if color.is_opaque() {
+                    self.buffer[index] = self.encode(color);
+                } else if color.alpha() > 0 {
+                    self.buffer[index] = self.blend_pixel(color, self.buffer[index]);
+                }
+            }
+        }
+    }
+
+    // Mezcla `color` usando como alfa la cobertura (0-255) del rectángulo
+    // `rect` = (x, y, ancho, alto) de `mask`, con su esquina en (x, y)
+    pub fn blend_mask(&mut self, mask: &[u8], mask_width: usize, rect: (usize, usize, usize, usize), x: isize, y: isize, color: Color) {
+        let (mask_x, mask_y, width, height) = rect;
+        let (x0, y0, x1, y1) = match self.clip_rect(x, y, width, height) {
+            Some(rect) => rect,
+            None => return,
+        };
+        let alpha = color.alpha() as u32;
+
+        for dy in y0..y1 {
+            let src_start = (mask_y + (dy as isize - y) as usize) * mask_width + mask_x + (x0 as isize - x) as usize;
+            let src = &mask[src_start..src_start + (x1 - x0)];
+            for (index, &coverage) in (dy * self.width + x0..dy * self.width + x1).zip(src) {
+                let coverage = ((coverage as u32 * alpha + 127) / 255) as u8;
+                if coverage == 255 {
+                    self.buffer[index] = self.encode(color);
+                } else if coverage > 0 {
+                    self.buffer[index] = self.blend_pixel(color.with_alpha(coverage), self.buffer[index]);
+                }
+            }
+        }
     }
 
     pub fn fill_polygon(&mut self, vertices: &Vec<[isize; 2]>, fill_color: Color) {
+        self.scan_polygon(vertices, |fb, x0, x1, y| fb.hline(x0, x1, y, fill_color));
+    }
+
+    // Rellena el polígono mezclando `fill_color` con lo que ya hay en el
+    // framebuffer; `alpha` es la opacidad del relleno (0.0 - 1.0)
+    pub fn fill_polygon_blend(&mut self, vertices: &Vec<[isize; 2]>, fill_color: Color, alpha: f32) {
+        let color = fill_color.with_alpha((alpha.clamp(0.0, 1.0) * 255.0) as u8);
+        self.scan_polygon(vertices, |fb, x0, x1, y| fb.blend_rect(x0, y, (x1 - x0 + 1) as usize, 1, color));
+    }
+
+    // Recorre por líneas de barrido el interior del polígono; `span` recibe
+    // cada tramo horizontal (x0, x1 incluidos, y)
+    fn scan_polygon<F: FnMut(&mut Self, isize, isize, isize)>(&mut self, vertices: &Vec<[isize; 2]>, mut span: F) {
+        if vertices.is_empty() {
+            return;
+        }
+
         let min_y = vertices.iter().map(|v| v[1]).min().unwrap_or(0);
         let max_y = vertices.iter().map(|v| v[1]).max().unwrap_or(0);
 
@@ -92,11 +378,8 @@ impl Framebuffer {
             }
             nodes.sort();
             for n in (0..nodes.len()).step_by(2) {
-                if n + 1 < nodes.len() {
-                    for x in nodes[n]..=nodes[n + 1] {
-                        self.set_current_color(fill_color);
-                        self.point(x, y);
-                    }
+                if n + 1 < nodes.len() && nodes[n] <= nodes[n + 1] {
+                    span(self, nodes[n], nodes[n + 1], y);
                 }
             }
         }
@@ -105,108 +388,284 @@ impl Framebuffer {
     pub fn get_pixel_color(&self, x: isize, y: isize) -> Option<Color> {
         if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
             let index = (self.width * y as usize) + x as usize;
-            Some(Color::from_hex(self.buffer[index]))
+            Some(self.decode(self.buffer[index]))
         } else {
             None
         }
     }
 
-    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: Color, scale: f32) {
-        // Cargar la fuente desde el archivo
-        let font_data = include_bytes!("../fonts/mai10.ttf");
-        let font = Font::try_from_bytes(font_data as &[u8]).unwrap();
-    
-        // Definir la escala (tamaño) del texto
-        let scale = Scale::uniform(scale); // Ajusta el tamaño según sea necesario
-    
-        // Establecer el color actual del framebuffer
-        self.set_current_color(color);
-    
-        // Obtener métricas verticales de la fuente
+    // Divide el framebuffer en hasta `count` franjas de filas disjuntas
+    pub fn row_bands(&mut self, count: usize) -> Vec<RowBand<'_>> {
+        let width = self.width;
+        let rows_per_band = self.height.div_ceil(count.max(1)).max(1);
+        self.buffer
+            .chunks_mut(rows_per_band * width.max(1))
+            .enumerate()
+            .map(|(i, pixels)| RowBand { top: i * rows_per_band, width, pixels })
+            .collect()
+    }
+
+    // Copia `source` estirada para cubrir todo el framebuffer (vista 3D
+    // dibujada a menor resolución)
+    pub fn draw_scaled(&mut self, source: &Framebuffer) {
+        if (source.width, source.height) == (self.width, self.height) {
+            self.buffer.copy_from_slice(&source.buffer);
+            return;
+        }
+        stretch_into(&source.buffer, source.width, source.height, &mut self.buffer, self.width, (0, 0, self.width, self.height));
+    }
+
+    // Dibuja la capa transparente `layer` con su esquina superior izquierda en
+    // (x, y), multiplicando su alfa por `opacity`
+    pub fn composite(&mut self, layer: &Framebuffer, x: isize, y: isize, opacity: f32) {
+        let (x0, y0, x1, y1) = match self.clip_rect(x, y, layer.width, layer.height) {
+            Some(rect) => rect,
+            None => return,
+        };
+        let opacity = opacity.clamp(0.0, 1.0);
+
+        for dy in y0..y1 {
+            let src_start = (dy as isize - y) as usize * layer.width + (x0 as isize - x) as usize;
+            let src = &layer.buffer[src_start..src_start + (x1 - x0)];
+            for (index, &pixel) in (dy * self.width + x0..dy * self.width + x1).zip(src) {
+                let mut color = layer.decode(pixel);
+                if opacity < 1.0 {
+                    color = color.with_alpha((color.alpha() as f32 * opacity).round() as u8);
+                }
+                if color.is_opaque() {
+                    self.buffer[index] = self.encode(color);
+                } else if color.alpha() > 0 {
+                    self.buffer[index] = self.blend_pixel(color, self.buffer[index]);
+                }
+            }
+        }
+    }
+
+    // Ancho y alto en píxeles que ocupa `text` dibujado con `font` a `scale`
+    pub fn text_size(font: &Font, text: &str, scale: f32) -> (usize, usize) {
+        let scale = Scale::uniform(scale);
         let v_metrics = font.v_metrics(scale);
-    
-        // Crear una posición de punto para el layout
-        let start_point = point(x as f32, y as f32 + v_metrics.ascent);
-    
-        // Generar los glifos posicionados para el texto dado
-        let glyphs: Vec<PositionedGlyph> = font.layout(text, scale, start_point).collect();
-    
-        // Dibujar cada glifo en el framebuffer
-        for glyph in glyphs {
-            if let Some(bounding_box) = glyph.pixel_bounding_box() {
-                glyph.draw(|gx, gy, gv| {
-                    let px = gx as i32 + bounding_box.min.x;
-                    let py = gy as i32 + bounding_box.min.y;
-    
-                    // Verificar que el valor del glifo sea suficientemente alto para dibujar
-                    if gv > 0.1 { // Ajusta el umbral según sea necesario
-                        self.point(px as isize, py as isize);
-                    }
-                });
-            }
-        }
-    }
-
-    pub fn draw_image(&mut self, image_path: &str, width: usize, height: usize) {
-        // Cargar la imagen
-        let img = image::open(image_path).expect("No se pudo cargar la imagen");
-        let img_width = img.width() as usize;
-        let img_height = img.height() as usize;
-    
-        // Calcular el factor de escala para ajustar la imagen al ancho del framebuffer
-        let scale_factor = width as f32 / img_width as f32;
-    
-        // Mantener la relación de aspecto y calcular la nueva altura
-        let new_height = (img_height as f32 * scale_factor) as usize;
-    
-        // Redimensionar la imagen manteniendo la relación de aspecto
-        let scaled_img = img.resize_exact(width as u32, new_height as u32, FilterType::Lanczos3);
-    
-        // Crear un buffer para el framebuffer
-        let mut buffer: Vec<u32> = vec![0; width * height];
-    
-        // Calcular el offset vertical para centrar la imagen en el framebuffer
-        let vertical_offset = (height.saturating_sub(new_height)) / 2;
-    
-        // Dibujar la imagen redimensionada en el framebuffer, centrada verticalmente
-        for y in 0..new_height.min(height) {
-            for x in 0..width {
-                let pixel = scaled_img.get_pixel(x as u32, y as u32);
-                let rgba = pixel.0;
-                let r = rgba[0] as u32;
-                let g = rgba[1] as u32;
-                let b = rgba[2] as u32;
-                let a = rgba[3] as u32;
-    
-                // Insertar el píxel en el buffer con el offset vertical
-                self.buffer[((y + vertical_offset) * width + x) as usize] = (a << 24) | (r << 16) | (g << 8) | b;
-            }
-        }
-    }
-
-    pub fn draw_image_at_position(&mut self, image_path: &str, width: usize, height: usize, pos_x: usize, pos_y: usize) {
-        // Cargar la imagen
-        let img = image::open(image_path).expect("No se pudo cargar la imagen");
-        
-        // Redimensionar la imagen a las dimensiones proporcionadas (width, height)
-        let resized_img = img.resize_exact(width as u32, height as u32, FilterType::Lanczos3);
-        
-        // Dibujar la imagen en el framebuffer en la posición especificada (pos_x, pos_y)
-        for y in 0..height {
-            for x in 0..width {
-                // Asegurarse de que los píxeles estén dentro de los límites del framebuffer
-                if pos_x + x < self.width && pos_y + y < self.height {
-                    let pixel = resized_img.get_pixel(x as u32, y as u32);
-                    let rgba = pixel.0;
-                    let r = rgba[0] as u32;
-                    let g = rgba[1] as u32;
-                    let b = rgba[2] as u32;
-                    let a = rgba[3] as u32;
-    
-                    // Insertar el píxel en el framebuffer en la posición correcta
-                    self.buffer[((y + pos_y) * self.width + (x + pos_x)) as usize] = (a << 24) | (r << 16) | (g << 8) | b;
+        let width = font
+            .layout(text, scale, point(0.0, 0.0))
+            .last()
+            .map(|glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+            .unwrap_or(0.0);
+        (width.ceil() as usize, (v_metrics.ascent - v_metrics.descent).ceil() as usize)
+    }
+
+    // Dibuja una imagen ya escalada centrada en el framebuffer
+    pub fn draw_image(&mut self, image: &Texture) {
+        let horizontal_offset = self.width.saturating_sub(image.width) / 2;
+        let vertical_offset = self.height.saturating_sub(image.height) / 2;
+        self.draw_image_at_position(image, horizontal_offset, vertical_offset);
+    }
+
+    // Dibuja una imagen ya escalada en la posición (pos_x, pos_y)
+    pub fn draw_image_at_position(&mut self, image: &Texture, pos_x: usize, pos_y: usize) {
+        self.blit_texture_scaled(image, pos_x as isize, pos_y as isize, image.width, image.height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_scaling_letterboxes_whole_multiples() {
+        assert_eq!(viewport(320, 200, 1000, 800, Scaling::Integer), (20, 100, 960, 600));
+        assert_eq!(viewport(320, 200, 1000, 800, Scaling::Fractional), (0, 87, 1000, 625));
+        // Ventana más pequeña que la resolución interna
+        assert_eq!(viewport(1000, 800, 500, 400, Scaling::Integer), (0, 0, 500, 400));
+    }
+
+    #[test]
+    fn window_positions_map_back_through_the_letterbox() {
+        let mut framebuffer = Framebuffer::new(320, 200);
+        framebuffer.set_scaling(Scaling::Integer);
+        assert_eq!(framebuffer.window_to_framebuffer(20.0, 100.0, 1000, 800), (0.0, 0.0));
+        assert_eq!(framebuffer.window_to_framebuffer(500.0, 400.0, 1000, 800), (160.0, 100.0));
+
+        framebuffer.set_scaling(Scaling::Fractional);
+        assert_eq!(framebuffer.window_to_framebuffer(1000.0, 712.0, 1000, 800), (320.0, 200.0));
+        // Misma ventana que la resolución interna: sin cambios
+        assert_eq!(framebuffer.window_to_framebuffer(17.0, 3.0, 320, 200), (17.0, 3.0));
+    }
+
+    #[test]
+    fn scale_into_repeats_pixels_and_clears_bars() {
+        let src = [1, 2, 3, 4];
+        let mut dst = [9; 5 * 4];
+        scale_into(&src, 2, 2, &mut dst, 5, 4, Scaling::Integer);
+
+        assert_eq!(dst, [
+            1, 1, 2, 2, 0,
+            1, 1, 2, 2, 0,
+            3, 3, 4, 4, 0,
+            3, 3, 4, 4, 0,
+        ]);
+    }
+
+    fn pattern_texture(width: usize, height: usize) -> Texture {
+        let data = (0..width * height)
+            .map(|i| {
+                let alpha = if i % 7 == 0 { 0 } else if i % 5 == 0 { 128 } else { 255 };
+                Color::new_rgba((i * 37 % 256) as i32, (i * 11 % 256) as i32, (i * 3 % 256) as i32, alpha)
+            })
+            .collect();
+        Texture::new(width, height, data)
+    }
+
+    fn gradient(width: usize, height: usize) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(width, height);
+        for (i, pixel) in framebuffer.buffer.iter_mut().enumerate() {
+            *pixel = (i as u32).wrapping_mul(0x0001_0203) & 0x00FF_FFFF;
+        }
+        framebuffer
+    }
+
+    #[test]
+    fn span_primitives_match_the_point_path() {
+        let color = Color::new(10, 200, 30);
+        let translucent = Color::new_rgba(250, 20, 90, 100);
+        let texture = pattern_texture(16, 8);
+
+        // Rectángulos que se salen por todos los bordes
+        for &(x, y, w, h) in &[(-5, -3, 12, 9), (30, 20, 40, 40), (10, 5, 0, 4), (-50, 0, 10, 10)] {
+            let mut expected = gradient(40, 30);
+            let mut actual = gradient(40, 30);
+            expected.set_current_color(color);
+            for py in y..y + h as isize {
+                for px in x..x + w as isize {
+                    expected.point(px, py);
+                }
+            }
+            actual.fill_rect(x, y, w, h, color);
+            assert_eq!(expected.buffer, actual.buffer);
+
+            for py in y..y + h as isize {
+                for px in x..x + w as isize {
+                    expected.blend_point(px, py, translucent);
+                }
+            }
+            actual.blend_rect(x, y, w, h, translucent);
+            assert_eq!(expected.buffer, actual.buffer);
+        }
+
+        // Escalado entero, fraccionario y recortado
+        for &(x, y, w, h) in &[(3, 4, 16, 8), (-7, 2, 33, 21), (25, 20, 24, 24)] {
+            let mut expected = gradient(40, 30);
+            let mut actual = gradient(40, 30);
+            for dy in 0..h {
+                for dx in 0..w {
+                    let color = texture.get_color(dx * texture.width / w, dy * texture.height / h);
+                    expected.blend_point(x + dx as isize, y + dy as isize, color);
                 }
             }
+            actual.blit_texture_scaled(&texture, x, y, w, h);
+            assert_eq!(expected.buffer, actual.buffer);
+        }
+
+        // La columna cruza los límites entre franjas y se sale por abajo
+        let mut expected = gradient(40, 30);
+        let mut actual = gradient(40, 30);
+        for y in 2..34isize {
+            let texture_y = ((y as f32 - 2.0) * 0.3) as usize;
+            expected.set_current_color(texture.get_color(5, texture_y));
+            expected.point(12, y);
+        }
+        for mut band in actual.row_bands(4) {
+            band.vline_textured(12, 2, 34, &texture, 5, 0.3);
         }
-    }    
+        assert_eq!(expected.buffer, actual.buffer);
+    }
+
+    #[test]
+    fn compositing_a_layer_matches_drawing_directly() {
+        let opaque = Color::new(10, 200, 30);
+        let translucent = Color::new_rgba(250, 20, 90, 100);
+        let texture = pattern_texture(16, 8);
+
+        let mut expected = gradient(40, 30);
+        expected.fill_rect(5, 3, 7, 6, opaque);
+        expected.blend_rect(14, 0, 9, 12, translucent);
+        expected.blit_texture_scaled(&texture, 5, 14, 32, 16);
+
+        // La misma escena en una capa desplazada que se sale por la derecha
+        let mut layer = Framebuffer::new_transparent(40, 30);
+        layer.fill_rect(-3, 3, 10, 6, opaque);
+        layer.blend_rect(9, 0, 9, 12, translucent);
+        layer.blit_texture_scaled(&texture, 0, 14, 32, 16);
+        let mut actual = gradient(40, 30);
+        actual.composite(&layer, 5, 0, 1.0);
+        assert_eq!(expected.buffer, actual.buffer);
+
+        let mut hidden = gradient(40, 30);
+        hidden.composite(&layer, 5, 0, 0.0);
+        assert_eq!(gradient(40, 30).buffer, hidden.buffer);
+    }
+
+    // Compara los primitivos con el camino píxel a píxel. Ejecutar con
+    // `cargo test --release -- --ignored --nocapture span_benchmarks`
+    #[test]
+    #[ignore]
+    fn span_benchmarks() {
+        use std::time::Instant;
+
+        const FRAMES: usize = 100;
+        let color = Color::new(120, 40, 200);
+        let translucent = color.with_alpha(100);
+        let texture = pattern_texture(64, 64);
+        let mut framebuffer = Framebuffer::new(1000, 800);
+
+        let mut bench = |name: &str, draw: &mut dyn FnMut(&mut Framebuffer)| {
+            let start = Instant::now();
+            for _ in 0..FRAMES {
+                draw(&mut framebuffer);
+            }
+            println!("{:<28} {:>8.3} ms/frame", name, start.elapsed().as_secs_f64() * 1000.0 / FRAMES as f64);
+        };
+
+        bench("fill: point", &mut |fb| {
+            fb.set_current_color(color);
+            for y in 0..800 {
+                for x in 0..1000 {
+                    fb.point(x, y);
+                }
+            }
+        });
+        bench("fill: fill_rect", &mut |fb| fb.fill_rect(0, 0, 1000, 800, color));
+
+        bench("blend: blend_point", &mut |fb| {
+            for y in 0..800 {
+                for x in 0..1000 {
+                    fb.blend_point(x, y, translucent);
+                }
+            }
+        });
+        bench("blend: blend_rect", &mut |fb| fb.blend_rect(0, 0, 1000, 800, translucent));
+
+        bench("blit: blend_point", &mut |fb| {
+            for y in 0..800 {
+                for x in 0..1000 {
+                    fb.blend_point(x as isize, y as isize, texture.get_color(x * 64 / 1000, y * 64 / 800));
+                }
+            }
+        });
+        bench("blit: blit_texture_scaled", &mut |fb| fb.blit_texture_scaled(&texture, 0, 0, 1000, 800));
+
+        bench("walls: point", &mut |fb| {
+            for x in 0..1000 {
+                for y in 100..700 {
+                    fb.set_current_color(texture.get_color(x % 64, ((y - 100) as f32 * 0.1) as usize));
+                    fb.point(x as isize, y);
+                }
+            }
+        });
+        bench("walls: vline_textured", &mut |fb| {
+            let mut bands = fb.row_bands(1);
+            for x in 0..1000 {
+                bands[0].vline_textured(x, 100, 700, &texture, x % 64, 0.1);
+            }
+        });
+    }
 }
@@ -0,0 +1,270 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+use rusttype::{point, Font, PositionedGlyph, Scale};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+// Ancho del atlas; crece en alto cuando se llena
+const ATLAS_WIDTH: usize = 1024;
+
+// Glifo rasterizado dentro del atlas. `offset` es la esquina superior izquierda
+// del glifo respecto a su punto de origen en la línea base.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct AtlasEntry {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    offset_x: isize,
+    offset_y: isize,
+}
+
+// Cobertura (0-255) de los glifos ya rasterizados, colocados en estantes:
+// filas de alto variable que se llenan de izquierda a derecha
+struct GlyphAtlas {
+    pixels: Vec<u8>,
+    height: usize,
+    // Clave: (glifo, bits del tamaño). `None` para glifos sin píxeles (espacios)
+    entries: HashMap<(u16, u32), Option<AtlasEntry>>,
+    cursor_x: usize,
+    cursor_y: usize,
+    shelf_height: usize,
+}
+
+impl GlyphAtlas {
+    fn new() -> Self {
+        GlyphAtlas {
+            pixels: Vec::new(),
+            height: 0,
+            entries: HashMap::new(),
+            cursor_x: 0,
+            cursor_y: 0,
+            shelf_height: 0,
+        }
+    }
+
+    // Reserva un hueco de `width` x `height`; `None` si no cabe a lo ancho
+    fn allocate(&mut self, width: usize, height: usize) -> Option<(usize, usize)> {
+        if width > ATLAS_WIDTH {
+            return None;
+        }
+        if self.cursor_x + width > ATLAS_WIDTH {
+            self.cursor_x = 0;
+            self.cursor_y += self.shelf_height;
+            self.shelf_height = 0;
+        }
+        if self.cursor_y + height > self.height {
+            self.height = (self.cursor_y + height).max(self.height * 2);
+            self.pixels.resize(ATLAS_WIDTH * self.height, 0);
+        }
+
+        let slot = (self.cursor_x, self.cursor_y);
+        // Un píxel de separación entre glifos
+        self.cursor_x += width + 1;
+        self.shelf_height = self.shelf_height.max(height + 1);
+        Some(slot)
+    }
+
+    // Entrada del glifo, rasterizándolo la primera vez que se pide. El glifo se
+    // rasteriza en el origen, así que la posición se redondea al píxel.
+    // `Err` si es demasiado ancho para el atlas.
+    fn entry(&mut self, glyph: &PositionedGlyph, size: f32) -> Result<Option<AtlasEntry>, ()> {
+        let key = (glyph.id().0, size.to_bits());
+        if let Some(entry) = self.entries.get(&key) {
+            return Ok(*entry);
+        }
+
+        let glyph = glyph.unpositioned().clone().positioned(point(0.0, 0.0));
+        let entry = match glyph.pixel_bounding_box() {
+            Some(bounding_box) => {
+                let width = bounding_box.width() as usize;
+                let height = bounding_box.height() as usize;
+                let (x, y) = self.allocate(width, height).ok_or(())?;
+                glyph.draw(|gx, gy, coverage| {
+                    self.pixels[(y + gy as usize) * ATLAS_WIDTH + x + gx as usize] = (coverage * 255.0).round() as u8;
+                });
+                Some(AtlasEntry {
+                    x,
+                    y,
+                    width,
+                    height,
+                    offset_x: bounding_box.min.x as isize,
+                    offset_y: bounding_box.min.y as isize,
+                })
+            }
+            None => None,
+        };
+
+        self.entries.insert(key, entry);
+        Ok(entry)
+    }
+}
+
+// Fuente con su caché de glifos. Cada fuente tiene su propio atlas, así que
+// los glifos quedan identificados por fuente, tamaño y glifo. Se usa como una
+// `Font` normal para medir el texto.
+pub struct CachedFont {
+    font: Font<'static>,
+    atlas: RefCell<GlyphAtlas>,
+}
+
+impl CachedFont {
+    pub fn new(font: Font<'static>) -> Self {
+        CachedFont { font, atlas: RefCell::new(GlyphAtlas::new()) }
+    }
+
+    // Como el `blend_text` de las pruebas de texto, pero copiando los glifos
+    // del atlas en lugar de rasterizarlos en cada llamada
+    pub fn draw(&self, framebuffer: &mut Framebuffer, x: isize, y: isize, text: &str, color: Color, size: f32) {
+        let scale = Scale::uniform(size);
+        let ascent = self.font.v_metrics(scale).ascent;
+        let mut atlas = self.atlas.borrow_mut();
+
+        for glyph in self.font.layout(text, scale, point(0.0, ascent)) {
+            let origin = glyph.position();
+            let origin_x = x + origin.x.round() as isize;
+            let origin_y = y + origin.y.round() as isize;
+
+            match atlas.entry(&glyph, size) {
+                Ok(Some(entry)) => framebuffer.blend_mask(
+                    &atlas.pixels,
+                    ATLAS_WIDTH,
+                    (entry.x, entry.y, entry.width, entry.height),
+                    origin_x + entry.offset_x,
+                    origin_y + entry.offset_y,
+                    color,
+                ),
+                Ok(None) => {}
+                // Glifo enorme: se rasteriza directamente
+                Err(()) => {
+                    let glyph = glyph.unpositioned().clone().positioned(point(origin_x as f32, origin_y as f32));
+                    if let Some(bounding_box) = glyph.pixel_bounding_box() {
+                        glyph.draw(|gx, gy, coverage| {
+                            let alpha = (coverage * color.alpha() as f32).round() as u8;
+                            if alpha > 0 {
+                                framebuffer.blend_point(gx as isize + bounding_box.min.x as isize, gy as isize + bounding_box.min.y as isize, color.with_alpha(alpha));
+                            }
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Deref for CachedFont {
+    type Target = Font<'static>;
+
+    fn deref(&self) -> &Font<'static> {
+        &self.font
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::UiFont;
+    use crate::text::tests::blend_text;
+
+    fn load(font: UiFont) -> CachedFont {
+        CachedFont::new(Font::try_from_vec(std::fs::read(font.path()).unwrap()).unwrap())
+    }
+
+    fn painted(framebuffer: &Framebuffer) -> usize {
+        framebuffer.get_buffer().iter().filter(|&&pixel| pixel != 0).count()
+    }
+
+    #[test]
+    fn glyphs_are_rasterised_once_per_size() {
+        let font = load(UiFont::Pixel);
+        let white = Color::new(255, 255, 255);
+        let mut first = Framebuffer::new(300, 60);
+        let mut second = Framebuffer::new(300, 60);
+
+        font.draw(&mut first, 4, 4, "Level 1", white, 30.0);
+        let glyphs = font.atlas.borrow().entries.len();
+        // "Level 1": L, e, v, l, espacio y 1
+        assert_eq!(glyphs, 6);
+
+        font.draw(&mut second, 4, 4, "Level 1", white, 30.0);
+        assert_eq!(font.atlas.borrow().entries.len(), glyphs);
+        assert_eq!(first.get_buffer(), second.get_buffer());
+
+        font.draw(&mut second, 4, 30, "Level", white, 20.0);
+        assert_eq!(font.atlas.borrow().entries.len(), glyphs + 4);
+    }
+
+    #[test]
+    fn cached_text_covers_the_same_area_as_direct_text() {
+        let font = load(UiFont::Meditative);
+        let color = Color::new(255, 255, 255);
+        let mut direct = Framebuffer::new(400, 80);
+        let mut cached = Framebuffer::new(400, 80);
+
+        blend_text(&mut direct, &font, 10, 10, "Press ENTER to start game", color, 32.0);
+        font.draw(&mut cached, 10, 10, "Press ENTER to start game", color, 32.0);
+
+        // Los glifos del atlas se colocan en píxeles enteros, así que solo
+        // cambian los bordes
+        let (direct, cached) = (painted(&direct), painted(&cached));
+        assert!(direct.abs_diff(cached) * 10 < direct, "direct {} cached {}", direct, cached);
+    }
+
+    #[test]
+    fn the_atlas_grows_when_a_shelf_is_full() {
+        let mut atlas = GlyphAtlas::new();
+        let mut slots = Vec::new();
+        for _ in 0..40 {
+            slots.push(atlas.allocate(100, 30).unwrap());
+        }
+
+        assert_eq!(slots[0], (0, 0));
+        assert_eq!(slots[1], (101, 0));
+        // Diez glifos de 100 + 1 por estante
+        assert_eq!(slots[10], (0, 31));
+        assert!(atlas.height >= 3 * 31 + 30);
+        assert_eq!(atlas.pixels.len(), ATLAS_WIDTH * atlas.height);
+        assert!(atlas.allocate(ATLAS_WIDTH + 1, 10).is_none());
+    }
+
+    // Tiempo de dibujar los textos de la pantalla de título y de la selección
+    // de nivel. Ejecutar con
+    // `cargo test --release -- --ignored --nocapture text_benchmarks`
+    #[test]
+    #[ignore]
+    fn text_benchmarks() {
+        use std::time::Instant;
+
+        const FRAMES: usize = 100;
+        let font = load(UiFont::Pixel);
+        let white = Color::new(255, 255, 255);
+        let mut framebuffer = Framebuffer::new(1000, 800);
+        let labels = [
+            ("Press ENTER to start game", 70.0),
+            ("Press S for settings", 40.0),
+            ("Level selector", 60.0),
+            ("Level 1", 60.0),
+            ("Level 2", 60.0),
+            ("Level 3", 60.0),
+            ("FPS: 60.00", 20.0),
+        ];
+
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            for (i, (text, size)) in labels.iter().enumerate() {
+                blend_text(&mut framebuffer, &font, 20, 20 + i as isize * 100, text, white, *size);
+            }
+        }
+        println!("{:<28} {:>8.3} ms/frame", "text: rasterised", start.elapsed().as_secs_f64() * 1000.0 / FRAMES as f64);
+
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            for (i, (text, size)) in labels.iter().enumerate() {
+                font.draw(&mut framebuffer, 20, 20 + i as isize * 100, text, white, *size);
+            }
+        }
+        println!("{:<28} {:>8.3} ms/frame", "text: glyph atlas", start.elapsed().as_secs_f64() * 1000.0 / FRAMES as f64);
+    }
+}
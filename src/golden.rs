@@ -0,0 +1,245 @@
+// Pruebas de regresión visual: escenas fijas renderizadas sin ventana y
+// comparadas con imágenes de referencia guardadas en `tests/golden`.
+//
+// Para regenerar las referencias después de un cambio visual intencionado:
+// `GOLDEN_BLESS=1 cargo test golden`
+// Si una escena no coincide, se escriben la imagen obtenida y una imagen de
+// diferencias en `target/golden`.
+
+use crate::bmp::reader::{read_bmp_file, BmpError, BmpImage};
+use crate::bmp::{write_bmp_file, BmpFormat};
+use crate::color::Color;
+use crate::doors::{exit_texture, Doors};
+use crate::enemy::Enemy;
+use crate::exploration::Exploration;
+use crate::framebuffer::Framebuffer;
+use crate::glyph_cache::CachedFont;
+use crate::layer::CachedLayer;
+use crate::maze::{draw_enemies_position, draw_player_position, minimap, render3d, render_view, MapTransform};
+use crate::occupancy::Occupancy;
+use crate::player::Player;
+use crate::sprite::{Clip, SpriteSheet, GUARD_SHEET};
+use crate::text::{draw_line, draw_text_box, Align, TextStyle, UiFont};
+use crate::texture::Texture;
+
+use nalgebra_glm::Vec2;
+use rusttype::Font;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const REFERENCE_DIR: &str = "tests/golden";
+const OUTPUT_DIR: &str = "target/golden";
+const BLESS_VAR: &str = "GOLDEN_BLESS";
+
+// Resolución de las escenas: pequeña para que las referencias ocupen poco
+const WIDTH: usize = 160;
+const HEIGHT: usize = 120;
+const BLOCK_SIZE: f32 = 32.0;
+
+// Diferencia máxima por canal que se acepta en un píxel (redondeos de coma
+// flotante entre plataformas) y fracción de píxeles que puede superarla
+struct Tolerance {
+    channel: u8,
+    pixels: f32,
+}
+
+const EXACT: Tolerance = Tolerance { channel: 2, pixels: 0.0 };
+// El texto depende del rasterizado de rusttype en los bordes de los glifos
+const TEXT: Tolerance = Tolerance { channel: 8, pixels: 0.002 };
+
+// Escena fija: laberinto, cámara y guardias
+struct Scene {
+    maze: Vec<Vec<char>>,
+    player: Player,
+    guards: Vec<Enemy>,
+}
+
+impl Scene {
+    // `player` y `guards` en celdas (columna, fila); se colocan en su centro
+    fn new(rows: &[&str], player: (f32, f32), angle: f32, guards: &[(f32, f32)]) -> Self {
+        let center = |(col, row): (f32, f32)| Vec2::new((col + 0.5) * BLOCK_SIZE, (row + 0.5) * BLOCK_SIZE);
+        let pos = center(player);
+        Scene {
+            maze: rows.iter().map(|row| row.chars().collect()).collect(),
+            player: Player::new(pos.x, pos.y, angle, std::f32::consts::PI / 3.0),
+            guards: guards.iter().map(|&cell| Enemy::new(center(cell), 0.0, 0.0, 22.5, 100.0)).collect(),
+        }
+    }
+}
+
+// Pasillo con una celda ('!') a la izquierda y la salida ('/') al fondo
+fn corridor(guards: &[(f32, f32)]) -> Scene {
+    let rows = [
+        "+---+---+",
+        "|       |",
+        "|  !!   |",
+        "|       /",
+        "|       |",
+        "+---+---+",
+    ];
+    Scene::new(&rows, (1.0, 3.0), 0.0, guards)
+}
+
+fn texture(path: &str) -> Texture {
+    Texture::from_image(&image::open(path).unwrap())
+}
+
+fn font(choice: UiFont) -> CachedFont {
+    CachedFont::new(Font::try_from_vec(std::fs::read(choice.path()).unwrap()).unwrap())
+}
+
+// Píxeles distintos entre lo renderizado y la referencia
+struct Mismatch {
+    pixels: usize,
+    max_difference: u8,
+    diff: Vec<u32>,
+}
+
+fn channel_difference(a: u32, b: u32) -> u8 {
+    (0..3).map(|i| ((a >> (i * 8)) as u8).abs_diff((b >> (i * 8)) as u8)).max().unwrap_or(0)
+}
+
+// Compara canal a canal. La imagen de diferencias marca en rojo los píxeles
+// fuera de tolerancia sobre la imagen obtenida oscurecida.
+fn compare(actual: &[u32], expected: &[u32], tolerance: u8) -> Mismatch {
+    let mut mismatch = Mismatch { pixels: 0, max_difference: 0, diff: Vec::with_capacity(actual.len()) };
+    for (&a, &b) in actual.iter().zip(expected) {
+        let difference = channel_difference(a, b);
+        mismatch.max_difference = mismatch.max_difference.max(difference);
+        if difference > tolerance {
+            mismatch.pixels += 1;
+            mismatch.diff.push(0x00FF_0000);
+        } else {
+            mismatch.diff.push((a >> 2) & 0x003F_3F3F);
+        }
+    }
+    mismatch
+}
+
+// Compara `framebuffer` con `tests/golden/<name>.bmp`, o la reescribe si se
+// definió `GOLDEN_BLESS`
+fn check(name: &str, framebuffer: &Framebuffer, tolerance: Tolerance) {
+    let reference = PathBuf::from(REFERENCE_DIR).join(format!("{}.bmp", name));
+    let (width, height, buffer) = (framebuffer.get_width(), framebuffer.get_height(), framebuffer.get_buffer());
+
+    if std::env::var_os(BLESS_VAR).is_some() {
+        std::fs::create_dir_all(REFERENCE_DIR).unwrap();
+        write_bmp_file(&reference.to_string_lossy(), buffer, width, height, BmpFormat::Rgb24).unwrap();
+        return;
+    }
+
+    let expected: BmpImage = match read_bmp_file(&reference.to_string_lossy()) {
+        Ok(image) => image,
+        Err(BmpError::Io(e)) => panic!("missing reference '{}' ({}); run with {}=1 to create it", reference.display(), e, BLESS_VAR),
+        Err(e) => panic!("{}: {}", reference.display(), e),
+    };
+    assert_eq!((expected.width, expected.height), (width, height), "{}: reference size differs", name);
+
+    let mismatch = compare(buffer, &expected.pixels, tolerance.channel);
+    if mismatch.pixels as f32 > tolerance.pixels * buffer.len() as f32 {
+        std::fs::create_dir_all(OUTPUT_DIR).unwrap();
+        let actual_path = PathBuf::from(OUTPUT_DIR).join(format!("{}-actual.bmp", name));
+        let diff_path = PathBuf::from(OUTPUT_DIR).join(format!("{}-diff.bmp", name));
+        write_bmp_file(&actual_path.to_string_lossy(), buffer, width, height, BmpFormat::Rgb24).unwrap();
+        write_bmp_file(&diff_path.to_string_lossy(), &mismatch.diff, width, height, BmpFormat::Rgb24).unwrap();
+        panic!(
+            "{}: {} pixels differ from the reference (max channel difference {}); see '{}' and '{}'",
+            name,
+            mismatch.pixels,
+            mismatch.max_difference,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn golden_render3d_walls_cell_and_exit() {
+    let scene = corridor(&[]);
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    let mut wall_heights = Vec::new();
+    let (wall, cell, door) = (texture("textures/prison_wall.png"), texture("textures/Cell.png"), texture("textures/Door.jpeg"));
+    let exit = exit_texture(&door);
+
+    render3d(&mut framebuffer, &scene.player, &scene.maze, BLOCK_SIZE, &wall, &cell, &door, &exit, &Doors::new(&scene.maze), &mut wall_heights);
+    check("render3d_corridor", &framebuffer, EXACT);
+}
+
+#[test]
+fn golden_render_enemy_in_front_of_walls() {
+    // Un guardia en el pasillo y otro oculto detrás de la celda
+    let scene = corridor(&[(5.0, 3.0), (3.5, 1.0)]);
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    let (wall, cell, door) = (texture("textures/prison_wall.png"), texture("textures/Cell.png"), texture("textures/Door.jpeg"));
+    let exit = exit_texture(&door);
+    let sheet = SpriteSheet::new(Rc::new(texture(GUARD_SHEET)));
+    let guards = sheet.guard_sprites(&scene.guards, &[Clip::Idle; 2], scene.player.pos, 0.0);
+
+    render_view(&mut framebuffer, &scene.player, &scene.maze, BLOCK_SIZE, [&wall, &cell, &door, &exit], &Doors::new(&scene.maze), &guards);
+    check("render_enemy_corridor", &framebuffer, EXACT);
+}
+
+#[test]
+fn golden_render3d_door_half_open() {
+    // Puerta a medio abrir en el pasillo, con la salida detrás
+    let mut scene = Scene::new(&["+---+---+", "|   D   /", "+---+---+"], (1.0, 1.0), 0.1, &[]);
+    let mut doors = Doors::new(&scene.maze);
+    doors.interact(4, 1, &[]);
+    for _ in 0..10 {
+        doors.update(1.0 / 30.0, &mut scene.maze, |_, _| false);
+    }
+
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    let mut wall_heights = Vec::new();
+    let (wall, cell, door) = (texture("textures/prison_wall.png"), texture("textures/Cell.png"), texture("textures/Door.jpeg"));
+    let exit = exit_texture(&door);
+
+    render3d(&mut framebuffer, &scene.player, &scene.maze, BLOCK_SIZE, &wall, &cell, &door, &exit, &doors, &mut wall_heights);
+    check("render3d_door_half_open", &framebuffer, EXACT);
+}
+
+#[test]
+fn golden_minimap_with_player_and_guard() {
+    let scene = corridor(&[(3.0, 3.0)]);
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.fill_rect(0, 0, WIDTH, HEIGHT, Color::new(60, 90, 120));
+
+    let mut occupancy = Occupancy::new(BLOCK_SIZE);
+    occupancy.rebuild(scene.player.pos, &scene.guards);
+    let mut exploration = Exploration::new(&scene.maze, scene.guards.len());
+    let transform = MapTransform::corner(BLOCK_SIZE);
+
+    minimap(&mut framebuffer, &mut CachedLayer::new(), &scene.maze, 0.5, &occupancy, &mut exploration, &transform);
+    draw_enemies_position(&mut framebuffer, &scene.guards, &occupancy, &transform);
+    draw_player_position(&mut framebuffer, scene.player.pos, &transform);
+    check("minimap_corridor", &framebuffer, EXACT);
+}
+
+#[test]
+fn golden_text_styles() {
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.fill_rect(0, 0, WIDTH, HEIGHT, Color::new(40, 60, 80));
+    let white = Color::new(255, 255, 255);
+
+    let pixel = font(UiFont::Pixel);
+    draw_line(&mut framebuffer, &pixel, 4, 4, "Plain", &TextStyle::new(16.0, white));
+    draw_line(&mut framebuffer, &pixel, 156, 4, "Shadow", &TextStyle::new(16.0, white).aligned(Align::Right).with_shadow(Color::new(0, 0, 0)));
+    draw_line(&mut framebuffer, &pixel, 80, 28, "Outline", &TextStyle::new(20.0, Color::new(240, 200, 40)).aligned(Align::Center).with_outline(Color::new(0, 0, 0)));
+
+    let meditative = font(UiFont::Meditative);
+    let style = TextStyle::new(18.0, white).aligned(Align::Center);
+    draw_text_box(&mut framebuffer, &meditative, 8, 56, 144, "Reach the exit without being seen", &style);
+    check("text_styles", &framebuffer, TEXT);
+}
+
+#[test]
+fn mismatches_are_counted_and_marked_in_red() {
+    let expected = [0x0010_2030, 0x0000_0000, 0x00FF_FFFF];
+    let actual = [0x0011_2030, 0x0000_0000, 0x0000_0000];
+
+    let mismatch = compare(&actual, &expected, 2);
+    assert_eq!(mismatch.pixels, 1);
+    assert_eq!(mismatch.max_difference, 255);
+    assert_eq!(mismatch.diff[2], 0x00FF_0000);
+    assert_eq!(mismatch.diff[0], 0x0004_080C);
+}
@@ -0,0 +1,181 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::glyph_cache::CachedFont;
+use crate::layer::CachedLayer;
+use crate::layout::{Anchor, Layout};
+use crate::text::{draw_line, Align, TextStyle};
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+// Velocidad a la que se llena el indicador de detección mientras algún
+// guardia ve al jugador y a la que se vacía cuando nadie lo ve, por segundo
+const DETECTION_RISE: f32 = 1.5;
+const DETECTION_DECAY: f32 = 0.35;
+
+// Medidas en píxeles de la resolución de referencia
+const BAR_WIDTH: f32 = 220.0;
+const BAR_HEIGHT: f32 = 14.0;
+const MARGIN: f32 = 20.0;
+
+// Lo que muestra el HUD en un frame
+pub struct HudState<'a> {
+    pub objective: &'a str,
+    pub elapsed: Duration,
+    pub items: &'a [String],
+    pub stamina: f32,
+    pub prompt: Option<&'a str>,
+}
+
+// Interfaz de la partida dibujada sobre la vista 3D. Se dibuja en una capa que
+// solo se rehace cuando cambia algo visible (el tiempo cambia una vez por
+// segundo).
+pub struct Hud {
+    layer: CachedLayer,
+    detection: f32,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Hud { layer: CachedLayer::new(), detection: 0.0 }
+    }
+
+    // `seen` indica si algún guardia ve al jugador en este frame
+    pub fn update_detection(&mut self, seen: bool, delta_time: f32) {
+        let change = if seen { DETECTION_RISE } else { -DETECTION_DECAY };
+        self.detection = (self.detection + change * delta_time).clamp(0.0, 1.0);
+    }
+
+    pub fn detection(&self) -> f32 {
+        self.detection
+    }
+
+    pub fn draw(&mut self, framebuffer: &mut Framebuffer, font: &CachedFont, state: &HudState) {
+        // Los valores continuos se redondean a lo que se nota en pantalla
+        let mut hasher = DefaultHasher::new();
+        state.objective.hash(&mut hasher);
+        state.elapsed.as_secs().hash(&mut hasher);
+        state.items.hash(&mut hasher);
+        ((state.stamina * 100.0) as u32).hash(&mut hasher);
+        state.prompt.hash(&mut hasher);
+        ((self.detection * 100.0) as u32).hash(&mut hasher);
+
+        let detection = self.detection;
+        let width = framebuffer.get_width();
+        let height = framebuffer.get_height();
+        let layer = self.layer.get(width, height, hasher.finish(), |layer| draw_hud(layer, font, state, detection));
+        framebuffer.composite(layer, 0, 0, 1.0);
+    }
+}
+
+// Nombre y color del nivel de detección
+fn detection_status(detection: f32) -> (&'static str, Color) {
+    if detection >= 1.0 {
+        ("Detected", Color::new(230, 40, 40))
+    } else if detection > 0.0 {
+        ("Suspicious", Color::new(240, 200, 40))
+    } else {
+        ("Hidden", Color::new(80, 200, 120))
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+// Barra con fondo translúcido, relleno `fraction` y borde de 1 píxel
+fn draw_bar(layer: &mut Framebuffer, x: usize, y: usize, width: usize, height: usize, fraction: f32, color: Color) {
+    let (x, y) = (x as isize, y as isize);
+    layer.blend_rect(x, y, width, height, Color::new_rgba(0, 0, 0, 140));
+    layer.fill_rect(x, y, (width as f32 * fraction.clamp(0.0, 1.0)) as usize, height, color);
+
+    let border = Color::new(255, 255, 255).with_alpha(180);
+    layer.blend_rect(x - 1, y - 1, width + 2, 1, border);
+    layer.blend_rect(x - 1, y + height as isize, width + 2, 1, border);
+    layer.blend_rect(x - 1, y, 1, height, border);
+    layer.blend_rect(x + width as isize, y, 1, height, border);
+}
+
+fn draw_hud(layer: &mut Framebuffer, font: &CachedFont, state: &HudState, detection: f32) {
+    let layout = Layout::for_framebuffer(layer);
+    let text = TextStyle::new(layout.size(22.0), Color::new(255, 255, 255)).with_outline(Color::new(0, 0, 0));
+    let small = text.with_size(layout.size(18.0));
+    let bar_width = layout.size(BAR_WIDTH) as usize;
+    let bar_height = layout.size(BAR_HEIGHT).max(4.0) as usize;
+    let line = |style: &TextStyle| Framebuffer::text_size(font, "", style.size).1;
+
+    // Detección: arriba en el centro
+    let (status, color) = detection_status(detection);
+    let (bar_x, bar_y) = layout.place(Anchor::Top, 0.0, MARGIN, bar_width, bar_height);
+    draw_bar(layer, bar_x, bar_y, bar_width, bar_height, detection, color);
+    let status_style = small.aligned(Align::Center);
+    draw_line(layer, font, (bar_x + bar_width / 2) as isize, (bar_y + bar_height + 4) as isize, status, &TextStyle { color, ..status_style });
+
+    // Tiempo y objetivo: arriba a la derecha
+    let right = (layer.get_width() as f32 - layout.size(MARGIN)) as isize;
+    let top = layout.size(MARGIN) as isize;
+    draw_line(layer, font, right, top, &format_elapsed(state.elapsed), &text.aligned(Align::Right));
+    draw_line(layer, font, right, top + line(&text) as isize + 4, state.objective, &small.aligned(Align::Right));
+
+    // Aguante: abajo a la izquierda
+    let (stamina_x, stamina_y) = layout.place(Anchor::BottomLeft, MARGIN, MARGIN, bar_width, bar_height);
+    let stamina_color = if state.stamina < 0.3 { Color::new(230, 120, 40) } else { Color::new(70, 160, 230) };
+    draw_bar(layer, stamina_x, stamina_y, bar_width, bar_height, state.stamina, stamina_color);
+    draw_line(layer, font, stamina_x as isize, stamina_y as isize - line(&small) as isize - 4, "Stamina", &small);
+
+    // Objetos recogidos: abajo a la derecha, el más reciente abajo
+    let items_bottom = layer.get_height() as isize - layout.size(MARGIN) as isize;
+    let step = line(&small) as isize + 2;
+    let mut items: Vec<&str> = state.items.iter().map(|item| item.as_str()).collect();
+    if items.is_empty() {
+        items.push("No items");
+    }
+    for (i, item) in items.iter().rev().enumerate() {
+        draw_line(layer, font, right, items_bottom - (i as isize + 1) * step, item, &small.aligned(Align::Right));
+    }
+
+    // Indicación según lo que hay delante: abajo en el centro
+    if let Some(prompt) = state.prompt {
+        let style = text.aligned(Align::Center);
+        let (_, prompt_y) = layout.place(Anchor::Bottom, 0.0, 110.0, 0, line(&style));
+        draw_line(layer, font, (layer.get_width() / 2) as isize, prompt_y as isize, prompt, &style);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detection_rises_while_seen_and_fades_slowly() {
+        let mut hud = Hud::new();
+        let frame = 1.0 / 60.0;
+
+        for _ in 0..20 {
+            hud.update_detection(true, frame);
+        }
+        assert_eq!(detection_status(hud.detection()).0, "Suspicious");
+        for _ in 0..40 {
+            hud.update_detection(true, frame);
+        }
+        assert_eq!(detection_status(hud.detection()).0, "Detected");
+
+        // Vaciarse tarda más que llenarse
+        for _ in 0..60 {
+            hud.update_detection(false, frame);
+        }
+        assert!(hud.detection() > 0.5);
+        for _ in 0..200 {
+            hud.update_detection(false, frame);
+        }
+        assert_eq!(detection_status(hud.detection()).0, "Hidden");
+    }
+
+    #[test]
+    fn elapsed_time_is_minutes_and_seconds() {
+        assert_eq!(format_elapsed(Duration::from_secs(0)), "00:00");
+        assert_eq!(format_elapsed(Duration::from_millis(754_900)), "12:34");
+    }
+}
@@ -0,0 +1,57 @@
+use crate::framebuffer::Framebuffer;
+
+// Capa fuera de pantalla que solo se vuelve a dibujar cuando cambia lo que
+// muestra. `key` identifica ese contenido (por ejemplo, la revisión de la
+// exploración); con la misma clave y el mismo tamaño se reutiliza tal cual.
+pub struct CachedLayer {
+    layer: Framebuffer,
+    key: Option<u64>,
+}
+
+impl CachedLayer {
+    pub fn new() -> Self {
+        CachedLayer {
+            layer: Framebuffer::new_transparent(0, 0),
+            key: None,
+        }
+    }
+
+    // Fuerza a redibujar la capa en el siguiente `get`
+    pub fn invalidate(&mut self) {
+        self.key = None;
+    }
+
+    pub fn get<F: FnOnce(&mut Framebuffer)>(&mut self, width: usize, height: usize, key: u64, draw: F) -> &Framebuffer {
+        let resized = (self.layer.get_width(), self.layer.get_height()) != (width, height);
+        if resized {
+            self.layer = Framebuffer::new_transparent(width, height);
+        }
+        if resized || self.key != Some(key) {
+            self.layer.clear();
+            draw(&mut self.layer);
+            self.key = Some(key);
+        }
+        &self.layer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redraws_only_when_the_key_or_size_changes() {
+        let mut cache = CachedLayer::new();
+        let mut draws = 0;
+
+        for key in [1, 1, 2, 2, 2] {
+            cache.get(4, 4, key, |_| draws += 1);
+        }
+        assert_eq!(draws, 2);
+
+        cache.get(8, 4, 2, |_| draws += 1);
+        cache.invalidate();
+        cache.get(8, 4, 2, |_| draws += 1);
+        assert_eq!(draws, 4);
+    }
+}
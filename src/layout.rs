@@ -0,0 +1,79 @@
+use crate::framebuffer::Framebuffer;
+
+// Resolución para la que se diseñaron las pantallas; las medidas de la
+// interfaz se dan en píxeles de esta resolución
+pub const REFERENCE_WIDTH: f32 = 1000.0;
+pub const REFERENCE_HEIGHT: f32 = 800.0;
+
+// Punto de la pantalla al que se ancla un elemento
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    BottomLeft,
+    Bottom,
+}
+
+// Coloca elementos de la interfaz respecto a los bordes de la pantalla y
+// escala sus medidas con la resolución
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    width: usize,
+    height: usize,
+    scale: f32,
+}
+
+impl Layout {
+    pub fn new(width: usize, height: usize) -> Self {
+        let scale = (width as f32 / REFERENCE_WIDTH).min(height as f32 / REFERENCE_HEIGHT);
+        Layout { width, height, scale }
+    }
+
+    pub fn for_framebuffer(framebuffer: &Framebuffer) -> Self {
+        Layout::new(framebuffer.get_width(), framebuffer.get_height())
+    }
+
+    // Convierte una medida de la resolución de referencia a la actual
+    pub fn size(&self, reference: f32) -> f32 {
+        reference * self.scale
+    }
+
+    // Esquina superior izquierda de un elemento de `item_width` x `item_height`
+    // anclado en `anchor`. Los desplazamientos están en píxeles de referencia y
+    // apuntan hacia dentro de la pantalla desde el borde anclado; en el eje
+    // horizontal centrado se suman tal cual.
+    pub fn place(&self, anchor: Anchor, offset_x: f32, offset_y: f32, item_width: usize, item_height: usize) -> (usize, usize) {
+        let offset_x = self.size(offset_x);
+        let offset_y = self.size(offset_y);
+        let free_x = self.width as f32 - item_width as f32;
+        let free_y = self.height as f32 - item_height as f32;
+
+        let x = match anchor {
+            Anchor::TopLeft | Anchor::BottomLeft => offset_x,
+            Anchor::Top | Anchor::Bottom => free_x / 2.0 + offset_x,
+            Anchor::TopRight => free_x - offset_x,
+        };
+        let y = match anchor {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => offset_y,
+            Anchor::BottomLeft | Anchor::Bottom => free_y - offset_y,
+        };
+
+        (x.max(0.0) as usize, y.max(0.0) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchors_follow_the_screen_edges() {
+        let layout = Layout::new(2000, 1600);
+
+        assert_eq!(layout.size(20.0), 40.0);
+        assert_eq!(layout.place(Anchor::TopLeft, 10.0, 10.0, 100, 50), (20, 20));
+        assert_eq!(layout.place(Anchor::TopRight, 10.0, 10.0, 100, 50), (1880, 20));
+        assert_eq!(layout.place(Anchor::Bottom, 0.0, 25.0, 100, 50), (950, 1500));
+    }
+}
@@ -9,8 +9,27 @@ mod texture;
 mod polygon;
 mod line;
 mod enemy;
-mod audioPlayer;
+mod occupancy;
+mod exploration;
+mod audio_engine;
+mod audio_backend;
+mod positional_audio;
+mod footsteps;
+mod rng;
 mod scenes;
+mod assets;
+mod asset_pack;
+mod settings;
+mod layout;
+mod layer;
+mod text;
+mod glyph_cache;
+mod hud;
+mod capture;
+mod sprite;
+mod doors;
+#[cfg(test)]
+mod golden;
 
 use enemy::Enemy;
 use framebuffer::Framebuffer;
@@ -18,9 +37,13 @@ use texture::Texture;
 use color::Color;
 use player::Player;
 use polygon::Polygon;
-use audioPlayer::AudioPlayer;
+use audio_engine::AudioEngine;
 use line::Line;
 use scenes::{game_start};
+use assets::Assets;
+use asset_pack::AssetPack;
+use settings::Settings;
+use capture::{capture_frames, CaptureOptions};
 use maze::{render, render3d, render_enemies_pos, render_enemy, draw_player_position, draw_enemies_position, draw_enemy_fov, minimap};
 use minifb::{Window, WindowOptions, Key};
 use image::GenericImageView;
@@ -35,19 +58,51 @@ fn calculate_fps(start_time: Instant, frame_count: usize) -> f64 {
 
 fn main() {
     
-    let width = 1000;
-    let height = 800;
+    let mut settings = Settings::load();
+
+    // Modo sin ventana: `--capture-frames N` guarda imágenes de un recorrido
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match CaptureOptions::from_args(&args) {
+        Ok(Some(options)) => {
+            let mut assets = Assets::new(AssetPack::from_env());
+            if let Err(e) = capture_frames(&options, &settings, &mut assets) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+
+    let width = settings.width;
+    let height = settings.height;
     let mut framebuffer = Framebuffer::new(width, height);
+    framebuffer.set_scaling(settings.scaling);
     
     let mut window = Window::new(
         "Maze",
         width,
         height,
-        WindowOptions::default(),
+        WindowOptions {
+            resize: true,
+            ..WindowOptions::default()
+        },
     ).unwrap_or_else(|e| {
         panic!("{}", e);
     });
 
-    game_start(width, height, &mut framebuffer, &mut window);
+    let mut assets = Assets::new(AssetPack::from_env());
+
+    let mut audio = AudioEngine::from_env(settings.audio_backend);
+    settings.apply_audio(&mut audio);
+
+    if let Err(e) = game_start(width, height, &mut framebuffer, &mut window, &mut assets, &mut audio, &mut settings) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 
 }
\ No newline at end of file
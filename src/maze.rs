@@ -2,12 +2,16 @@ use crate::polygon::Polygon;
 use crate::line::Line;
 
 use crate::enemy::Enemy;
-use crate::fileReader::load_maze;
-use crate::framebuffer::Framebuffer;
+use crate::framebuffer::{Framebuffer, RowBand};
 use crate::color::Color;
-use crate::cast_ray::{cast_ray,cast_ray_enemy};
+use crate::cast_ray::{cast_ray, Intersect};
+use crate::doors::{is_door, Doors, DOOR, KEYCARD_DOOR, KEY_DOOR};
 use crate::player::Player;
 use crate::texture::Texture;
+use crate::occupancy::{EntityId, Occupancy};
+use crate::exploration::Exploration;
+use crate::layer::CachedLayer;
+use crate::sprite::{PlacedSprite, SpriteFrame};
 
 use std::collections::HashSet;
 use nalgebra_glm::Vec2;
@@ -18,29 +22,31 @@ use std::thread;
 fn draw_cell(framebuffer: &mut Framebuffer, x0: usize, y0: usize, block_size: usize, cell: char, opacity: f32) {
     let color = match cell {
         '+' => Color::new(5, 166, 114),   // Paredes
-        '|' | '-' | '!' | '/' => Color::new(5, 166, 114), // Paredes
+        '|' | '-' | '!' => Color::new(5, 166, 114), // Paredes
+        '/' => Color::new(230, 200, 40), // Salida
         'g' => Color::new(255, 255, 0), // Meta
         ' ' => Color::new(0, 0, 0), // Espacios vacíos
-        'p' | 'e' => Color::new(0, 0, 0), // Espacio del jugador
+        // Puertas, abiertas o cerradas, para que el minimapa guardado en caché
+        // no dependa de su estado
+        cell if is_door(cell) => Color::new(150, 100, 40),
         _ => Color::new(0, 0, 0),        // Color por defecto para caracteres desconocidos
     };
 
-    for y in 0..block_size {
-        for x in 0..block_size {
-            let bg_color = framebuffer.get_pixel_color((x0 + x) as isize, (y0 + y) as isize);
-            let blended_color = color.blend(bg_color.expect("REASON"), opacity);
-            if (cell == '|' || cell == '-' || cell == '+' || cell == '!' || cell == '/') {
-                framebuffer.set_current_color(Color::new(5, 166, 114));
-            } else {
-                framebuffer.set_current_color(blended_color);
-            }
-            framebuffer.point((x0 + x) as isize, (y0 + y) as isize);
-        }
+    if cell == '|' || cell == '-' || cell == '+' || cell == '!' || cell == '/' || is_door(cell) {
+        framebuffer.fill_rect(x0 as isize, y0 as isize, block_size, block_size, color);
+    } else {
+        // `opacity` es la parte del fondo que se conserva
+        let alpha = ((1.0 - opacity).clamp(0.0, 1.0) * 255.0).round() as u8;
+        framebuffer.blend_rect(x0 as isize, y0 as isize, block_size, block_size, color.with_alpha(alpha));
     }
 }
 
-pub fn render(framebuffer: &mut Framebuffer, file_path: &str, opacity: f32) -> (Vec<Vec<char>>, Vec2) {
-    let maze = load_maze(file_path);
+// Carga el mapa de tiles y devuelve la posición inicial del jugador. Las marcas
+// de aparición ('p' y 'e') y las llaves ('k' y 'c') se reemplazan por espacio:
+// en el mapa solo cambian las puertas y las entidades se registran en
+// `Occupancy`.
+pub fn render(framebuffer: &mut Framebuffer, level: &Vec<Vec<char>>, opacity: f32) -> (Vec<Vec<char>>, Vec2) {
+    let mut maze = level.clone();
     let rows = maze.len();
     let cols = maze[0].len();
 
@@ -53,6 +59,9 @@ pub fn render(framebuffer: &mut Framebuffer, file_path: &str, opacity: f32) -> (
             if maze[row][col] == 'p' {
                 player_pos = Vec2::new((col * block_size) as f32 + (block_size / 2) as f32, (row * block_size) as f32 + (block_size / 2) as f32);
             } 
+            if matches!(maze[row][col], 'p' | 'e' | 'k' | 'c') {
+                maze[row][col] = ' ';
+            }
         }
     }
 
@@ -60,8 +69,7 @@ pub fn render(framebuffer: &mut Framebuffer, file_path: &str, opacity: f32) -> (
 }
 
 
-pub fn render_enemies_pos(framebuffer: &mut Framebuffer, file_path: &str) -> Vec<Vec2> {
-    let maze = load_maze(file_path);
+pub fn render_enemies_pos(framebuffer: &mut Framebuffer, maze: &Vec<Vec<char>>) -> Vec<Vec2> {
     let rows = maze.len();
     let cols = maze[0].len();
 
@@ -83,12 +91,23 @@ pub fn render_enemies_pos(framebuffer: &mut Framebuffer, file_path: &str) -> Vec
     enemies_pos
 }
 
+// Alfa mínimo para que un píxel de sprite se dibuje
+const SPRITE_ALPHA_CUTOFF: u8 = 8;
+
+// Altura máxima de un sprite como fracción del alto de la vista: 150 píxeles
+// en la vista de 800 de la configuración por defecto
+const MAX_SPRITE_HEIGHT_FRACTION: f32 = 0.1875;
+
+// Altura máxima en pantalla de un sprite en una vista de `view_height` filas
+pub fn max_sprite_height(view_height: usize) -> f32 {
+    view_height as f32 * MAX_SPRITE_HEIGHT_FRACTION
+}
+
 pub fn render_enemy(
     framebuffer: &mut Framebuffer,
     player: &Player,
     pos: &Vec2,
-    z_buffer: &mut [f32],
-    enemy_texture: &Texture,
+    sprite: &SpriteFrame,
     wall_heights: &[usize],
     max_sprite_height: f32, // Altura máxima del sprite en la pantalla
     maze: &Vec<Vec<char>>,
@@ -126,8 +145,8 @@ pub fn render_enemy(
     let sprite_size = ((screen_height / sprite_d) * 40.0).min(max_sprite_height);
     let start_x = (screen_width / 2.0) + (sprite_a - player_a) * (screen_height / player.fov) - (sprite_size / 2.0);
 
-    // Desplazamiento hacia abajo
-    let min_offset_down = 15.0;
+    // Desplazamiento hacia abajo, proporcional a la vista como la altura máxima
+    let min_offset_down = max_sprite_height * 0.1;
     let max_offset_down = 200.0; // Ajusta el valor máximo según sea necesario
     let max_distance = 1.0; // Ajusta la distancia máxima según sea necesario
 
@@ -153,372 +172,802 @@ pub fn render_enemy(
 
     for x in start_x..end_x {
         for y in start_y..end_y {
-            let tx = (((x - start_x) * enemy_texture.width as usize) / sprite_size as usize) as u32;
-            let ty = (((y - start_y) * enemy_texture.height as usize) / sprite_size as usize) as u32;
-            let color = enemy_texture.get_color(tx as usize, ty as usize);
-
-            if color.to_hex() != 0x000000 { // Ajusta el color hexadecimal según sea necesario para el fondo transparente
-                framebuffer.set_current_color(color);
-                framebuffer.point(x as isize, y as isize);
+            let tx = (((x - start_x) * sprite.width) / sprite_size as usize) as u32;
+            let ty = (((y - start_y) * sprite.height) / sprite_size as usize) as u32;
+            let color = sprite.get_color(tx as usize, ty as usize);
+
+            // Prueba alfa: se descartan los píxeles casi transparentes y el
+            // resto se mezcla con lo que ya hay detrás del sprite
+            if color.alpha() > SPRITE_ALPHA_CUTOFF {
+                framebuffer.blend_point(x as isize, y as isize, color);
             }
         }
     }
 }
 
 
-pub fn render3d(
-    framebuffer: &mut Framebuffer,
+// Vista 3D con los guardias y las llaves, dibujada en `view` a su propia
+// resolución
+pub fn render_view(
+    view: &mut Framebuffer,
     player: &Player,
     maze: &Vec<Vec<char>>,
     block_size: f32,
-    texture: &Texture,
-    texture_cell: &Texture,
-    texture_door: &Texture,
-    wall_heights: &mut Vec<usize>,
+    textures: [&Texture; 4],
+    doors: &Doors,
+    sprites: &[PlacedSprite],
 ) {
-    let roof_color = Color::new(102, 102, 102);
-    let floor_color = Color::new(187, 187, 187);
-
-    let first_half: Vec<[isize; 2]> = vec![
-        [0, 0],
-        [framebuffer.get_width().try_into().unwrap(), 0],
-        [framebuffer.get_width().try_into().unwrap(), (framebuffer.get_height() / 2).try_into().unwrap()],
-        [0, (framebuffer.get_height() / 2).try_into().unwrap()],
-    ];
-
-    let second_half: Vec<[isize; 2]> = vec![
-        [0, (framebuffer.get_height() / 2).try_into().unwrap()],
-        [framebuffer.get_width().try_into().unwrap(), (framebuffer.get_height() / 2).try_into().unwrap()],
-        [framebuffer.get_width().try_into().unwrap(), framebuffer.get_height().try_into().unwrap()],
-        [0, framebuffer.get_height().try_into().unwrap()],
-    ];
+    let [texture_wall, texture_cell, texture_door, texture_exit] = textures;
+    let max_height = max_sprite_height(view.get_height());
+    let mut wall_heights = vec![0; view.get_width()];
+
+    render3d(view, player, maze, block_size, texture_wall, texture_cell, texture_door, texture_exit, doors, &mut wall_heights);
+
+    // Los sprites se mezclan con lo que hay detrás, así que se dibujan del más
+    // lejano al más cercano
+    let mut sorted: Vec<&PlacedSprite> = sprites.iter().collect();
+    sorted.sort_by(|a, b| nalgebra_glm::distance(&b.pos, &player.pos).total_cmp(&nalgebra_glm::distance(&a.pos, &player.pos)));
+    for sprite in sorted {
+        render_enemy(view, player, &sprite.pos, &sprite.frame, &wall_heights, max_height, maze, block_size);
+    }
+}
 
-    let num_rays = framebuffer.get_width();
-    let hw = framebuffer.get_width() as f32 / 2.0; // Half width
-    let hh = framebuffer.get_height() as f32 / 2.0; // Half height
-    let distance_to_projection_plane = hw / (player.fov / 2.0).tan(); // Distancia del jugador al plano de proyección
+// Tramo de pared visible en una columna de la vista 3D
+#[derive(Clone, Copy)]
+struct WallColumn<'a> {
+    top: usize,
+    bottom: usize,
+    texture: &'a Texture,
+    texture_x: usize,
+    texture_y_step: f32,
+}
 
-    framebuffer.polygon(&first_half, roof_color, roof_color);
-    framebuffer.polygon(&second_half, floor_color, floor_color);
+// Lanza el rayo de la columna `i` y calcula el tramo de pared que se ve
+fn cast_column<'a>(
+    i: usize,
+    num_rays: usize,
+    player: &Player,
+    maze: &Vec<Vec<char>>,
+    block_size: f32,
+    textures: [&'a Texture; 4],
+    doors: &Doors,
+    projection: (f32, f32), // Media altura y distancia al plano de proyección
+) -> Option<WallColumn<'a>> {
+    let (hh, distance_to_projection_plane) = projection;
+    let current_ray = i as f32 / num_rays as f32; // Ray proportion
+    let angle = player.a - (player.fov / 2.0) + (player.fov * current_ray);
+    let (intersect, door_x) = cast_view_ray(&player.pos, angle, maze, block_size, doors)?;
+
+    let distance_to_wall = intersect.distance; // Distance to wall
+    let corrected_distance = distance_to_wall * (angle - player.a).cos(); // Correct fish-eye effect
+    let stake_height = (block_size * distance_to_projection_plane / corrected_distance).min(hh * 2.0);
+
+    // Seleccionar la textura basada en el carácter
+    let [texture_wall, texture_cell, texture_door, texture_exit] = textures;
+    let texture = match intersect.character {
+        '!' => texture_cell,
+        '/' => texture_exit,
+        cell if is_door(cell) => texture_door,
+        _ => texture_wall,
+    };
 
-    wall_heights.clear();
-    wall_heights.resize(num_rays, framebuffer.get_height());
-
-    for i in 0..num_rays {
-        let current_ray = i as f32 / num_rays as f32; // Ray proportion
-        let angle = player.a - (player.fov / 2.0) + (player.fov * current_ray);
-        if let Some(intersect) = cast_ray(&player.pos, angle, maze, block_size, false, 1000.0, None) {
-            let distance_to_wall = intersect.distance; // Distance to wall
-            let corrected_distance = distance_to_wall * (angle - player.a).cos(); // Correct fish-eye effect
-            let stake_height = (block_size * distance_to_projection_plane / corrected_distance).min(hh * 2.0);
-
-            let stake_top = (hh - (stake_height / 2.0)) as usize;
-            let stake_bottom = (hh + (stake_height / 2.0)) as usize;
-
-            wall_heights[i] = stake_bottom;
-
-            // Seleccionar la textura basada en el carácter
-            let (texture, texture_width, texture_height) = match intersect.character {
-                ' ' => (texture, texture.width, texture.height),
-                '!' => (texture_cell, texture_cell.width, texture_cell.height),
-                '/' => (texture_door, texture_door.width, texture_door.height),
-                _ => (texture, texture.width, texture.height),
-            };
+    // Mapeo de textura para la pared; las puertas usan su propia coordenada,
+    // que se desplaza con la hoja al abrirse
+    let texture_x_step = texture.width as f32 / block_size;
+    let wall_x = door_x.unwrap_or(intersect.x % block_size);
+
+    Some(WallColumn {
+        top: (hh - (stake_height / 2.0)) as usize,
+        bottom: (hh + (stake_height / 2.0)) as usize,
+        texture,
+        texture_x: (wall_x * texture_x_step) as usize,
+        texture_y_step: texture.height as f32 / stake_height,
+    })
+}
 
-            let texture_width = texture_width as f32;
-            let texture_height = texture_height as f32;
+// Posición del choque a lo largo de la cara de la celda que ha tocado el rayo
+fn face_offset(intersect: &Intersect, col: usize, row: usize, block_size: f32) -> f32 {
+    let fx = intersect.x - col as f32 * block_size;
+    let fy = intersect.y - row as f32 * block_size;
+    if fx.min(block_size - fx) < fy.min(block_size - fy) {
+        fy
+    } else {
+        fx
+    }
+}
 
-            // Mapeo de textura para la pared
-            let texture_x_step = texture_width / block_size;
-            let texture_y_step = texture_height / stake_height;
+// Rayo de la vista 3D. Atraviesa el hueco de las puertas a medio abrir: la
+// hoja se desliza a lo largo de la cara y deja libre la parte de antes del
+// desplazamiento. Si choca con una puerta devuelve también la coordenada de
+// textura de la hoja en unidades del mundo.
+fn cast_view_ray(pos: &Vec2, angle: f32, maze: &Vec<Vec<char>>, block_size: f32, doors: &Doors) -> Option<(Intersect, Option<f32>)> {
+    const MAX_DISTANCE: f32 = 1000.0;
+    let direction = Vec2::new(angle.cos(), angle.sin());
+    let mut travelled = 0.0;
+
+    loop {
+        let origin = pos + direction * travelled;
+        let mut intersect = cast_ray(&origin, angle, maze, block_size, false, MAX_DISTANCE - travelled, None)?;
+        intersect.distance += travelled;
+        if !is_door(intersect.character) {
+            return Some((intersect, None));
+        }
 
-            let wall_x = intersect.x % block_size;
-            let mut texture_x = (wall_x * texture_x_step) as usize;
+        let col = (intersect.x / block_size) as usize;
+        let row = (intersect.y / block_size) as usize;
+        let slide = doors.openness(col, row) * block_size;
+        let along = face_offset(&intersect, col, row, block_size);
+        if along >= slide {
+            return Some((intersect, Some(along - slide)));
+        }
 
-            for y in stake_top..stake_bottom {
-                let texture_y = ((y as f32 - stake_top as f32) * texture_y_step) as usize;
-                let color = texture.get_color(texture_x, texture_y);
-                framebuffer.set_current_color(color);
-                framebuffer.point(i as isize, y as isize);
+        // Por el hueco: sigue desde donde el rayo sale de la celda
+        travelled = intersect.distance;
+        loop {
+            travelled += 1.0;
+            let point = pos + direction * travelled;
+            if travelled > MAX_DISTANCE {
+                return None;
+            }
+            if (point.x / block_size) as usize != col || (point.y / block_size) as usize != row || point.x < 0.0 || point.y < 0.0 {
+                break;
             }
         }
     }
 }
 
+// Dibuja el techo, el suelo y las paredes que caen dentro de una franja de filas
+fn shade_band(band: &mut RowBand, columns: &[Option<WallColumn>], height: usize, roof_color: u32, floor_color: u32) {
+    for y in band.top()..band.top() + band.rows() {
+        band.row_mut(y).fill(if y < height / 2 { roof_color } else { floor_color });
+    }
 
+    for (x, column) in columns.iter().enumerate() {
+        if let Some(column) = column {
+            band.vline_textured(x, column.top, column.bottom, column.texture, column.texture_x, column.texture_y_step);
+        }
+    }
+}
 
+// Hilos que usa la vista 3D
+fn render_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get()).min(8)
+}
 
+pub fn render3d(
+    framebuffer: &mut Framebuffer,
+    player: &Player,
+    maze: &Vec<Vec<char>>,
+    block_size: f32,
+    texture: &Texture,
+    texture_cell: &Texture,
+    texture_door: &Texture,
+    texture_exit: &Texture,
+    doors: &Doors,
+    wall_heights: &mut Vec<usize>,
+) {
+    render_walls(framebuffer, player, maze, block_size, [texture, texture_cell, texture_door, texture_exit], doors, wall_heights, render_threads());
+}
+
+// Vista 3D en dos fases: primero los rayos, repartidos por bloques de
+// columnas, y luego el texturizado, repartido por franjas de filas. Ningún
+// hilo escribe en la zona de otro, así que el resultado no depende de
+// `threads`.
+fn render_walls(
+    framebuffer: &mut Framebuffer,
+    player: &Player,
+    maze: &Vec<Vec<char>>,
+    block_size: f32,
+    textures: [&Texture; 4],
+    doors: &Doors,
+    wall_heights: &mut Vec<usize>,
+    threads: usize,
+) {
+    let roof_color = Color::new(102, 102, 102).to_hex();
+    let floor_color = Color::new(187, 187, 187).to_hex();
+
+    let width = framebuffer.get_width();
+    let height = framebuffer.get_height();
+    let num_rays = width;
+    let hw = width as f32 / 2.0; // Half width
+    let hh = height as f32 / 2.0; // Half height
+    let distance_to_projection_plane = hw / (player.fov / 2.0).tan(); // Distancia del jugador al plano de proyección
+    let threads = threads.max(1);
+
+    let mut columns: Vec<Option<WallColumn>> = vec![None; num_rays];
+    let columns_per_thread = num_rays.div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        for (chunk, slots) in columns.chunks_mut(columns_per_thread).enumerate() {
+            scope.spawn(move || {
+                for (offset, slot) in slots.iter_mut().enumerate() {
+                    let i = chunk * columns_per_thread + offset;
+                    *slot = cast_column(i, num_rays, player, maze, block_size, textures, doors, (hh, distance_to_projection_plane));
+                }
+            });
+        }
+    });
+
+    wall_heights.clear();
+    wall_heights.extend(columns.iter().map(|column| column.map_or(height, |column| column.bottom)));
+
+    let columns = &columns;
+    thread::scope(|scope| {
+        for mut band in framebuffer.row_bands(threads) {
+            scope.spawn(move || shade_band(&mut band, columns, height, roof_color, floor_color));
+        }
+    });
+}
 
 pub fn is_wall(maze: &Vec<Vec<char>>, x: usize, y: usize) -> (bool, char) {
     if y < maze.len() && x < maze[0].len() {
-        return (maze[y][x] == '+' || maze[y][x] == '|' || maze[y][x] == '-' || maze[y][x] == '!' || maze[y][x] == '/', maze[y][x])
+        let cell = maze[y][x];
+        return (matches!(cell, '+' | '|' | '-' | '!' | '/' | DOOR | KEY_DOOR | KEYCARD_DOOR), cell)
     } else {
         return (false, '\0')
     }
 }
 
-// Función para dibujar al jugador en el minimapa
-pub fn draw_player_position(framebuffer: &mut Framebuffer, player_pos: Vec2, block_size: usize, map_chosen: usize) {
-    let mut x_fix = 0;
-    let mut y_fix = 0;
-
-    if map_chosen == 0 {
-        x_fix = 1;
-        y_fix = 0;
-    } else if map_chosen == 1 {
-        x_fix = 20;
-        y_fix = 3;
-    } else {
-        x_fix = -7;
-        y_fix = -2;
+// Tamaño del minimapa de la esquina respecto al tamaño de bloque del mundo
+const MINIMAP_SCALE: f32 = 0.35;
+
+// Radio (en celdas) que el jugador descubre a su alrededor
+const VISIBLE_RADIUS: usize = 2;
+
+// Transformación de coordenadas del mundo a píxeles de un mapa 2D. Para el
+// minimapa giratorio, `pivot` es la posición del jugador y `rotation` hace
+// que su dirección de vista apunte hacia arriba.
+#[derive(Clone, Copy)]
+pub struct MapTransform {
+    origin_x: isize,
+    origin_y: isize,
+    cell_size: usize,
+    block_size: f32,
+    pivot: Vec2,
+    rotation: f32,
+    clip_radius: Option<f32>,
+}
+
+impl MapTransform {
+    // Minimapa de la esquina superior izquierda
+    pub fn corner(block_size: f32) -> Self {
+        MapTransform {
+            origin_x: 0,
+            origin_y: 0,
+            cell_size: ((block_size * MINIMAP_SCALE) as usize).max(1),
+            block_size,
+            pivot: Vec2::new(0.0, 0.0),
+            rotation: 0.0,
+            clip_radius: None,
+        }
     }
 
-    let player_size = 2;
-    let color = Color::new(0, 255, 0); // Verde para el jugador
+    // Mapa completo centrado en la pantalla, con un margen alrededor
+    pub fn full_screen(framebuffer: &Framebuffer, maze: &Vec<Vec<char>>, block_size: f32, margin: usize) -> Self {
+        let rows = maze.len().max(1);
+        let cols = maze.iter().map(|row| row.len()).max().unwrap_or(1).max(1);
+        let avail_w = framebuffer.get_width().saturating_sub(2 * margin);
+        let avail_h = framebuffer.get_height().saturating_sub(2 * margin);
+        let cell_size = std::cmp::min(avail_w / cols, avail_h / rows).max(1);
+
+        MapTransform {
+            origin_x: (framebuffer.get_width().saturating_sub(cols * cell_size) / 2) as isize,
+            origin_y: (framebuffer.get_height().saturating_sub(rows * cell_size) / 2) as isize,
+            cell_size,
+            block_size,
+            pivot: Vec2::new(0.0, 0.0),
+            rotation: 0.0,
+            clip_radius: None,
+        }
+    }
 
-    for y in -(player_size as isize)..=(player_size as isize) {
-        for x in -(player_size as isize)..=(player_size as isize) {
-            framebuffer.set_current_color(color);
-            framebuffer.point(((player_pos.x * 0.33) as isize + x + x_fix), ((player_pos.y * 0.33) as isize + y + y_fix));
+    // Minimapa circular centrado en el jugador que gira con la vista
+    pub fn rotating(center_x: isize, center_y: isize, radius: usize, player: &Player, block_size: f32) -> Self {
+        // El radio muestra las celdas visibles más un pequeño margen
+        let cells_in_radius = VISIBLE_RADIUS as f32 + 1.5;
+
+        MapTransform {
+            origin_x: center_x,
+            origin_y: center_y,
+            cell_size: ((radius as f32 / cells_in_radius) as usize).max(1),
+            block_size,
+            pivot: player.pos,
+            rotation: -(player.a + PI / 2.0),
+            clip_radius: Some(radius as f32),
         }
     }
-}
-// Función para dibujar la posición de los enemigos en el minimapa
-pub fn draw_enemies_position(framebuffer: &mut Framebuffer, enemies: &Vec<Enemy>, player_pos: Vec2, block_size: usize, map_width: usize, map_height: usize) {
-    let enemy_size = 2;
-    let color = Color::new(0, 0, 255); // Azul para los enemigos
 
-    let minimap_x = (player_pos.x * 0.35) as isize;
-    let minimap_y = (player_pos.y * 0.35) as isize;
+    pub fn cell_size(&self) -> usize {
+        self.cell_size
+    }
+
+    fn scale(&self) -> f32 {
+        self.cell_size as f32 / self.block_size
+    }
 
-    for enemy in enemies {
-        let enemy_pos = enemy.get_pos();
-        let enemy_x = (enemy_pos.x * 0.35) as isize;
-        let enemy_y = (enemy_pos.y * 0.35) as isize;
+    pub fn cell_origin(&self, col: usize, row: usize) -> (usize, usize) {
+        (
+            (self.origin_x + (col * self.cell_size) as isize) as usize,
+            (self.origin_y + (row * self.cell_size) as isize) as usize,
+        )
+    }
 
-        // Verifica si el enemigo está dentro del área visible del minimapa
-        if enemy_x >= minimap_x - block_size as isize && enemy_x <= minimap_x + block_size as isize &&
-           enemy_y >= minimap_y - block_size as isize && enemy_y <= minimap_y + block_size as isize {
-            for y in -(enemy_size as isize)..=(enemy_size as isize) {
-                for x in -(enemy_size as isize)..=(enemy_size as isize) {
-                    framebuffer.set_current_color(color);
-                    framebuffer.point(enemy_x + x, enemy_y + y);
+    pub fn to_screen(&self, pos: Vec2) -> (isize, isize) {
+        let (sin, cos) = self.rotation.sin_cos();
+        let dx = pos.x - self.pivot.x;
+        let dy = pos.y - self.pivot.y;
+        (
+            self.origin_x + ((dx * cos - dy * sin) * self.scale()).round() as isize,
+            self.origin_y + ((dx * sin + dy * cos) * self.scale()).round() as isize,
+        )
+    }
+
+    // Como `to_screen`, pero acerca al origen los puntos que quedan fuera del
+    // círculo del minimapa giratorio
+    pub fn to_screen_clipped(&self, pos: Vec2) -> (isize, isize) {
+        let (x, y) = self.to_screen(pos);
+        match self.clip_radius {
+            Some(radius) => {
+                let dx = (x - self.origin_x) as f32;
+                let dy = (y - self.origin_y) as f32;
+                let distance = (dx * dx + dy * dy).sqrt();
+                if distance > radius {
+                    let factor = radius / distance;
+                    (self.origin_x + (dx * factor) as isize, self.origin_y + (dy * factor) as isize)
+                } else {
+                    (x, y)
                 }
             }
+            None => (x, y),
         }
     }
+
+    pub fn to_world(&self, x: isize, y: isize) -> Vec2 {
+        let (sin, cos) = self.rotation.sin_cos();
+        let sx = (x - self.origin_x) as f32 / self.scale();
+        let sy = (y - self.origin_y) as f32 / self.scale();
+        Vec2::new(
+            self.pivot.x + sx * cos + sy * sin,
+            self.pivot.y - sx * sin + sy * cos,
+        )
+    }
 }
 
+fn draw_marker(framebuffer: &mut Framebuffer, x: isize, y: isize, size: isize, color: Color) {
+    let side = (2 * size + 1) as usize;
+    framebuffer.fill_rect(x - size, y - size, side, side, color);
+}
 
+// Función para dibujar al jugador en el minimapa
+pub fn draw_player_position(framebuffer: &mut Framebuffer, player_pos: Vec2, transform: &MapTransform) {
+    let (x, y) = transform.to_screen(player_pos);
+    draw_marker(framebuffer, x, y, 2, Color::new(0, 255, 0)); // Verde para el jugador
+}
+
+// Función para dibujar la posición de los enemigos en el minimapa
+pub fn draw_enemies_position(framebuffer: &mut Framebuffer, enemies: &Vec<Enemy>, occupancy: &Occupancy, transform: &MapTransform) {
+    let (player_col, player_row) = occupancy.player_cell().unwrap_or((0, 0));
 
-pub fn draw_enemy_fov(framebuffer: &mut Framebuffer, enemy: &Enemy, num_rays: usize, maze: &Vec<Vec<char>>, block_size: f32, enemy_in_map: bool, player_pos: Vec2, visible_radius: usize) {
-    // Calcula la posición del enemigo en términos de bloques
-    let enemy_block_x = (enemy.get_pos().x / block_size) as usize;
-    let enemy_block_y = (enemy.get_pos().y / block_size) as usize;
+    for (i, enemy) in enemies.iter().enumerate() {
+        let (enemy_col, enemy_row) = occupancy.cell_of(enemy.get_pos());
+
+        // Verifica si el enemigo está dentro del área visible del minimapa
+        if enemy_col.abs_diff(player_col) <= VISIBLE_RADIUS && enemy_row.abs_diff(player_row) <= VISIBLE_RADIUS {
+            if let Some(pos) = occupancy.position(EntityId::Enemy(i)) {
+                let (x, y) = transform.to_screen(pos);
+                draw_marker(framebuffer, x, y, 2, Color::new(0, 0, 255)); // Azul para los enemigos
+            }
+        }
+    }
+}
 
-    // Calcula la posición del jugador en términos de bloques
-    let player_block_x = (player_pos.x / block_size) as usize;
-    let player_block_y = (player_pos.y / block_size) as usize;
+// Mapa a pantalla completa con las zonas exploradas, salidas conocidas,
+// últimas posiciones vistas de los guardias y la dirección del jugador
+pub fn draw_full_map(framebuffer: &mut Framebuffer, maze: &Vec<Vec<char>>, exploration: &Exploration, player: &Player, block_size: f32) {
+    let transform = MapTransform::full_screen(framebuffer, maze, block_size, 40);
+    let cell_size = transform.cell_size();
 
-    // Verifica si el enemigo está dentro del rango visible del minimapa
-    let is_enemy_visible = (enemy_block_x as i32 - player_block_x as i32).abs() <= visible_radius as i32
-        && (enemy_block_y as i32 - player_block_y as i32).abs() <= visible_radius as i32;
+    draw_background(framebuffer, 0, 0, framebuffer.get_width(), framebuffer.get_height());
 
-    if enemy_in_map && is_enemy_visible {
-        for i in 0..num_rays {
-            let current_ray = i as f32 / num_rays as f32;
-            let angle = enemy.get_a() - ((PI / 8.0) / 2.0) + ((PI / 8.0) * current_ray);
-            cast_ray_enemy(&enemy.get_pos(), -angle, &maze, block_size, enemy_in_map, 100.0, 0.35, Some(framebuffer));
+    for (row, line) in maze.iter().enumerate() {
+        for (col, cell) in line.iter().enumerate() {
+            if exploration.is_explored(col, row) {
+                let (x0, y0) = transform.cell_origin(col, row);
+                draw_cell(framebuffer, x0, y0, cell_size, *cell, 0.5);
+            }
         }
     }
+
+    for (col, row) in exploration.known_exits(maze) {
+        let (x0, y0) = transform.cell_origin(col, row);
+        draw_cell(framebuffer, x0, y0, cell_size, 'g', 1.0);
+    }
+
+    let marker_size = (cell_size / 4).max(2) as isize;
+    for pos in exploration.last_seen_guards() {
+        let (x, y) = transform.to_screen(pos);
+        draw_marker(framebuffer, x, y, marker_size, Color::new(0, 0, 255));
+    }
+
+    // Flecha con la dirección del jugador
+    let (px, py) = transform.to_screen(player.pos);
+    let length = (cell_size as f32 * 0.8).max(6.0);
+    let point_at = |angle: f32, dist: f32| -> [isize; 2] {
+        [px + (angle.cos() * dist) as isize, py + (angle.sin() * dist) as isize]
+    };
+    let arrow = vec![
+        point_at(player.a, length),
+        point_at(player.a + 2.5, length * 0.6),
+        point_at(player.a - 2.5, length * 0.6),
+    ];
+    framebuffer.fill_polygon(&arrow, Color::new(0, 255, 0));
 }
 
 
-pub fn minimap(
-    framebuffer: &mut Framebuffer,
-    mut maze: Vec<Vec<char>>,
-    opacity: f32,
-    key_down: String,
-    direction: f32,
-    og_pos: Vec2,
-    new_pos: Vec2,
-    enemies: &mut Vec<Enemy>,
-    block_size: usize
-) -> (Vec<Vec<char>>, bool) {
-    let rows = maze.len();
-    let cols = maze[0].len();
 
-    let mut enemy_in_map = false;
+// Cono de visión del guardia calculado en el mundo contra la cuadrícula de
+// tiles. Devuelve la posición del guardia seguida de los extremos de cada rayo.
+pub fn enemy_vision_cone(enemy: &Enemy, num_rays: usize, maze: &Vec<Vec<char>>, block_size: f32) -> Vec<Vec2> {
+    let pos = enemy.get_pos();
+    let heading = enemy.heading();
+    let fov = enemy.get_fov_angle().to_radians();
+    let range = enemy.get_fov_range();
+
+    let mut points = vec![pos];
+    for i in 0..=num_rays {
+        let current_ray = i as f32 / num_rays.max(1) as f32;
+        let angle = heading - (fov / 2.0) + (fov * current_ray);
+        let distance = match cast_ray(&pos, angle, maze, block_size, false, range, None) {
+            Some(intersect) => intersect.distance,
+            None => range,
+        };
+        points.push(Vec2::new(pos.x + angle.cos() * distance, pos.y + angle.sin() * distance));
+    }
 
-    let scale_factor = 0.35;
-    let block_size = std::cmp::min(framebuffer.get_width() / cols, framebuffer.get_height() / rows);
-    let scaled_block_size = (block_size as f32 * scale_factor) as usize;
+    points
+}
 
-    // Calcula el tamaño total del minimapa
-    let minimap_width = cols * scaled_block_size;
-    let minimap_height = rows * scaled_block_size;
+// Dibuja el cono de visión del guardia en el minimapa como un polígono
+// translúcido, solo si el guardia está dentro del radio visible
+pub fn draw_enemy_fov(framebuffer: &mut Framebuffer, enemy: &Enemy, num_rays: usize, maze: &Vec<Vec<char>>, block_size: f32, occupancy: &Occupancy, transform: &MapTransform) {
+    let (player_col, player_row) = occupancy.player_cell().unwrap_or((0, 0));
+    let (enemy_col, enemy_row) = occupancy.cell_of(enemy.get_pos());
 
-    // Dibuja el fondo negro con opacidad
-    draw_background(framebuffer, 0, 0, minimap_width, minimap_height, opacity);
+    if enemy_col.abs_diff(player_col) > VISIBLE_RADIUS || enemy_row.abs_diff(player_row) > VISIBLE_RADIUS {
+        return;
+    }
+
+    let vertices: Vec<[isize; 2]> = enemy_vision_cone(enemy, num_rays, maze, block_size)
+        .iter()
+        .map(|point| {
+            let (x, y) = transform.to_screen_clipped(*point);
+            [x, y]
+        })
+        .collect();
+
+    framebuffer.fill_polygon_blend(&vertices, Color::new(255, 60, 60), 0.4);
+}
 
-    let og_pos_block_x = og_pos.x / block_size as f32;
-    let og_pos_block_y = og_pos.y / block_size as f32;
-    let new_pos_block_x = new_pos.x / block_size as f32;
-    let new_pos_block_y = new_pos.y / block_size as f32;
 
-    if new_pos_block_x.floor() != og_pos_block_x.floor() || new_pos_block_y.floor() != og_pos_block_y.floor() {
-        let key_down_str = key_down.as_str();
+// Modo del minimapa durante el juego
+#[derive(Clone, Copy, PartialEq)]
+pub enum MinimapMode {
+    Fixed,
+    Rotating,
+}
 
-        if key_down_str == "w" || key_down_str == "s" || key_down_str == "a" || key_down_str == "d" || key_down_str == "wawa" || key_down_str == "wdwd" || key_down_str == "sasa" || key_down_str == "sdsd"{
-            maze = update_minimap(maze, key_down_str.to_string(), direction, enemies, block_size);
+impl MinimapMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            MinimapMode::Fixed => MinimapMode::Rotating,
+            MinimapMode::Rotating => MinimapMode::Fixed,
         }
     }
+}
 
-    let mut player_pos = Vec2::new(0.0, 0.0);
-    let mut player_row = 0;
-    let mut player_col = 0;
+// Revela las celdas cercanas al jugador y registra los guardias a la vista.
+// Devuelve si hay algún enemigo dentro del radio visible.
+fn update_visibility(maze: &Vec<Vec<char>>, occupancy: &Occupancy, exploration: &mut Exploration) -> bool {
+    let rows = maze.len();
+    let cols = maze[0].len();
+    let mut enemy_in_map = false;
 
-    for row in 0..rows {
-        for col in 0..cols {
-            if maze[row][col] == 'p' {
-                player_row = row;
-                player_col = col;
-                player_pos = Vec2::new(col as f32 * scaled_block_size as f32, row as f32 * scaled_block_size as f32);
+    let (player_col, player_row) = occupancy.player_cell().unwrap_or((0, 0));
+    exploration.reveal_around(player_col, player_row, VISIBLE_RADIUS);
+
+    for row in (player_row.saturating_sub(VISIBLE_RADIUS))..(std::cmp::min(player_row + VISIBLE_RADIUS + 1, rows)) {
+        for col in (player_col.saturating_sub(VISIBLE_RADIUS))..(std::cmp::min(player_col + VISIBLE_RADIUS + 1, cols)) {
+            for id in occupancy.at(col, row) {
+                if let EntityId::Enemy(i) = id {
+                    enemy_in_map = true;
+                    if let Some(pos) = occupancy.position(*id) {
+                        exploration.see_guard(*i, pos);
+                    }
+                }
             }
         }
     }
 
-    let visible_radius = 2; // Radio de visión reducido
+    enemy_in_map
+}
 
-    for row in (player_row.saturating_sub(visible_radius))..(std::cmp::min(player_row + visible_radius + 1, rows)) {
-        for col in (player_col.saturating_sub(visible_radius))..(std::cmp::min(player_col + visible_radius + 1, cols)) {
-            let x0 = col * scaled_block_size;
-            let y0 = row * scaled_block_size;
+// Minimapa fijo. Las celdas se dibujan en `layer`, que solo se redibuja cuando
+// se exploran celdas nuevas, y la capa se compone sobre el framebuffer.
+pub fn minimap(
+    framebuffer: &mut Framebuffer,
+    layer: &mut CachedLayer,
+    maze: &Vec<Vec<char>>,
+    opacity: f32,
+    occupancy: &Occupancy,
+    exploration: &mut Exploration,
+    transform: &MapTransform,
+) -> bool {
+    let rows = maze.len();
+    let cols = maze[0].len();
 
-            // Dibuja la celda si está en el radio visible o si está en un borde visible según la lógica de visibilidad
-            let is_visible = (row as i32 - player_row as i32).abs() <= visible_radius.try_into().unwrap()
-                && (col as i32 - player_col as i32).abs() <= visible_radius.try_into().unwrap();
+    let scaled_block_size = transform.cell_size();
+    let enemy_in_map = update_visibility(maze, occupancy, exploration);
 
-            let is_border_cell = row == 0 || row == rows - 1 || col == 0 || col == cols - 1;
+    let exploration = &*exploration;
+    let map = layer.get(cols * scaled_block_size, rows * scaled_block_size, exploration.revision(), |layer| {
+        // Fondo negro con opacidad
+        draw_background(layer, 0, 0, cols * scaled_block_size, rows * scaled_block_size);
 
-            if is_visible || is_border_cell {
-                if maze[row][col] == 'e' {
-                    enemy_in_map = true;
+        // Todas las celdas exploradas, no solo las del radio visible
+        for row in 0..rows {
+            for col in 0..maze[row].len() {
+                if exploration.is_explored(col, row) {
+                    draw_cell(layer, col * scaled_block_size, row * scaled_block_size, scaled_block_size, maze[row][col], opacity);
                 }
-                draw_cell(framebuffer, x0, y0, scaled_block_size, maze[row][col], opacity);
             }
         }
-    }
+    });
+
+    let (map_x, map_y) = transform.cell_origin(0, 0);
+    framebuffer.composite(map, map_x as isize, map_y as isize, 1.0);
 
-    (maze, enemy_in_map)
+    enemy_in_map
 }
 
+// Minimapa circular centrado en el jugador. Gira con la vista, muestra el cono
+// de visión y se recorta al círculo de radio `radius` alrededor del origen de
+// `transform`. Se dibuja en la capa transparente `layer`, de lado
+// 2 * `radius` + 1, que luego se compone sobre el framebuffer.
+pub fn rotating_minimap(
+    framebuffer: &mut Framebuffer,
+    layer: &mut Framebuffer,
+    maze: &Vec<Vec<char>>,
+    opacity: f32,
+    occupancy: &Occupancy,
+    exploration: &mut Exploration,
+    transform: &MapTransform,
+    radius: usize,
+    fov: f32,
+) -> bool {
+    let enemy_in_map = update_visibility(maze, occupancy, exploration);
+
+    let wall_color = Color::new(5, 166, 114);
+    // El fondo conserva el 70% de lo que hay debajo y el suelo explorado
+    // oscurece además otro `opacity`
+    let background = Color::new_rgba(0, 0, 0, 77);
+    let floor_color = Color::new_rgba(0, 0, 0, ((1.0 - 0.7 * opacity.clamp(0.0, 1.0)) * 255.0).round() as i32);
+    let cone_color = Color::new_rgba(255, 255, 255, 64);
+
+    let r = radius as isize;
+    layer.clear();
+    let border = 2;
+    let cone_radius = (radius as f32 * 0.8) as isize;
+
+    for y in -r..=r {
+        for x in -r..=r {
+            let d2 = x * x + y * y;
+            if d2 > r * r {
+                continue;
+            }
 
+            let px = transform.origin_x + x;
+            let py = transform.origin_y + y;
 
-// Función para dibujar el fondo negro con opacidad
-fn draw_background(framebuffer: &mut Framebuffer, x: usize, y: usize, width: usize, height: usize, opacity: f32) {
-    let color = Color::new(0,0,0);
-    for i in 0..width {
-        for j in 0..height {
-            let bg_color = framebuffer.get_pixel_color((x + i) as isize, (y + j) as isize);
-            let blended_color = color.blend(bg_color.expect("REASON"), 0.7);
-            
-            framebuffer.set_current_color(blended_color);
+            let mut color = if d2 >= (r - border) * (r - border) {
+                wall_color
+            } else {
+                let world = transform.to_world(px, py);
+                let col = (world.x / transform.block_size).floor();
+                let row = (world.y / transform.block_size).floor();
+
+                if col < 0.0 || row < 0.0 || !exploration.is_explored(col as usize, row as usize) {
+                    background
+                } else if is_wall(maze, col as usize, row as usize).0 {
+                    wall_color
+                } else {
+                    floor_color
+                }
+            };
+
+            // Cuña del campo de visión: arriba es la dirección de la vista
+            let angle = (x as f32).atan2(-(y as f32));
+            if angle.abs() <= fov / 2.0 && d2 <= cone_radius * cone_radius && d2 < (r - border) * (r - border) {
+                color = cone_color.over(color);
+            }
 
-            framebuffer.point((x + i).try_into().unwrap(), (y + j).try_into().unwrap());
+            layer.blend_point(x + r, y + r, color);
         }
     }
+
+    framebuffer.composite(layer, transform.origin_x - r, transform.origin_y - r, 1.0);
+
+    enemy_in_map
 }
 
 
 
-fn update_minimap(mut maze: Vec<Vec<char>>, key_down: String, direction: f32, enemies: &mut Vec<Enemy>, block_size: usize) -> Vec<Vec<char>> {
-    let mut x_dir = direction.cos().round() as isize;  
-    let mut y_dir = direction.sin().round() as isize;
-
-    // Ajusta la dirección basada en la tecla presionada
-    match key_down.as_str() {
-        "w" => { /* No se cambia la dirección */ },
-        "s" => {
-            x_dir = -x_dir;
-            y_dir = -y_dir;
-        },
-        "a" => {
-            let angle = direction - std::f32::consts::FRAC_PI_2; // Gira 90 grados hacia la izquierda
-            x_dir = angle.cos().round() as isize;
-            y_dir = angle.sin().round() as isize;
-        },
-        "d" => {
-            let angle = direction + std::f32::consts::FRAC_PI_2; // Gira 90 grados hacia la derecha
-            x_dir = angle.cos().round() as isize;
-            y_dir = angle.sin().round() as isize;
-        },
-        "wawa" => {
-            let angle = direction - std::f32::consts::FRAC_PI_4; // Movimiento hacia adelante y diagonal
-            x_dir = (angle.cos() * 1.0).round() as isize;
-            y_dir = (angle.sin() * 1.0).round() as isize;
-
-        },
-        "wdwd" => {
-            let angle = direction + std::f32::consts::FRAC_PI_4; // Movimiento diagonal hacia adelante y hacia la derecha
-            x_dir = (angle.cos() * 1.0).round() as isize;
-            y_dir = (angle.sin() * 1.0).round() as isize;
-        },
-        "sasa" => {
-            let angle = direction - std::f32::consts::FRAC_PI_2 - std::f32::consts::FRAC_PI_4; // Movimiento diagonal hacia adelante y hacia la izquierda
-            x_dir = (angle.cos() * 1.0).round() as isize;
-            y_dir = (angle.sin() * 1.0).round() as isize;
-        },
-        "sdsd" => {
-            let angle = direction + std::f32::consts::FRAC_PI_2 + std::f32::consts::FRAC_PI_4; // Movimiento diagonal hacia atrás y hacia la derecha
-            x_dir = (angle.cos() * 1.0).round() as isize;
-            y_dir = (angle.sin() * 1.0).round() as isize;
-        },
-        _ => { return maze; } // Si no es una tecla válida, no hacer nada
-    }
-
-    // Encuentra la posición de 'p'
-    let mut p_pos = None;
-
-    for row in 0..maze.len() {
-        for col in 0..maze[row].len() {
-            if maze[row][col] == 'p' {
-                p_pos = Some((row as isize, col as isize));
-                break;
+// Fondo negro que conserva el 70% de lo que hay debajo
+fn draw_background(framebuffer: &mut Framebuffer, x: usize, y: usize, width: usize, height: usize) {
+    framebuffer.blend_rect(x as isize, y as isize, width, height, Color::new_rgba(0, 0, 0, 77));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Renderizador original de una sola pasada, columna a columna, que sirve
+    // de referencia para la versión paralela
+    fn render3d_serial(
+        framebuffer: &mut Framebuffer,
+        player: &Player,
+        maze: &Vec<Vec<char>>,
+        block_size: f32,
+        texture: &Texture,
+        texture_cell: &Texture,
+        texture_door: &Texture,
+        wall_heights: &mut Vec<usize>,
+    ) {
+        let roof_color = Color::new(102, 102, 102);
+        let floor_color = Color::new(187, 187, 187);
+
+        let first_half: Vec<[isize; 2]> = vec![
+            [0, 0],
+            [framebuffer.get_width().try_into().unwrap(), 0],
+            [framebuffer.get_width().try_into().unwrap(), (framebuffer.get_height() / 2).try_into().unwrap()],
+            [0, (framebuffer.get_height() / 2).try_into().unwrap()],
+        ];
+
+        let second_half: Vec<[isize; 2]> = vec![
+            [0, (framebuffer.get_height() / 2).try_into().unwrap()],
+            [framebuffer.get_width().try_into().unwrap(), (framebuffer.get_height() / 2).try_into().unwrap()],
+            [framebuffer.get_width().try_into().unwrap(), framebuffer.get_height().try_into().unwrap()],
+            [0, framebuffer.get_height().try_into().unwrap()],
+        ];
+
+        let num_rays = framebuffer.get_width();
+        let hw = framebuffer.get_width() as f32 / 2.0; // Half width
+        let hh = framebuffer.get_height() as f32 / 2.0; // Half height
+        let distance_to_projection_plane = hw / (player.fov / 2.0).tan(); // Distancia del jugador al plano de proyección
+
+        framebuffer.polygon(&first_half, roof_color, roof_color);
+        framebuffer.polygon(&second_half, floor_color, floor_color);
+
+        wall_heights.clear();
+        wall_heights.resize(num_rays, framebuffer.get_height());
+
+        for i in 0..num_rays {
+            let current_ray = i as f32 / num_rays as f32; // Ray proportion
+            let angle = player.a - (player.fov / 2.0) + (player.fov * current_ray);
+            if let Some(intersect) = cast_ray(&player.pos, angle, maze, block_size, false, 1000.0, None) {
+                let distance_to_wall = intersect.distance; // Distance to wall
+                let corrected_distance = distance_to_wall * (angle - player.a).cos(); // Correct fish-eye effect
+                let stake_height = (block_size * distance_to_projection_plane / corrected_distance).min(hh * 2.0);
+
+                let stake_top = (hh - (stake_height / 2.0)) as usize;
+                let stake_bottom = (hh + (stake_height / 2.0)) as usize;
+
+                wall_heights[i] = stake_bottom;
+
+                // Seleccionar la textura basada en el carácter
+                let (texture, texture_width, texture_height) = match intersect.character {
+                    ' ' => (texture, texture.width, texture.height),
+                    '!' => (texture_cell, texture_cell.width, texture_cell.height),
+                    '/' => (texture_door, texture_door.width, texture_door.height),
+                    _ => (texture, texture.width, texture.height),
+                };
+
+                let texture_width = texture_width as f32;
+                let texture_height = texture_height as f32;
+
+                // Mapeo de textura para la pared
+                let texture_x_step = texture_width / block_size;
+                let texture_y_step = texture_height / stake_height;
+
+                let wall_x = intersect.x % block_size;
+                let mut texture_x = (wall_x * texture_x_step) as usize;
+
+                for y in stake_top..stake_bottom {
+                    let texture_y = ((y as f32 - stake_top as f32) * texture_y_step) as usize;
+                    let color = texture.get_color(texture_x, texture_y);
+                    framebuffer.set_current_color(color);
+                    framebuffer.point(i as isize, y as isize);
+                }
             }
         }
     }
 
-    if let Some((row, col)) = p_pos {
-        let new_row = row + y_dir;
-        let new_col = col + x_dir;
-
-        // Verifica que la nueva posición esté dentro de los límites y sea un espacio vacío
-        if new_row >= 0 && new_row < maze.len() as isize &&
-           new_col >= 0 && new_col < maze[0].len() as isize &&
-           (maze[new_row as usize][new_col as usize] == ' ' || maze[new_row as usize][new_col as usize] == 'e'){
-            maze[new_row as usize][new_col as usize] = 'p';
-            maze[row as usize][col as usize] = ' ';
-        }
+    fn test_texture(seed: u32) -> Texture {
+        let data = (0..64 * 64u32)
+            .map(|i| Color::from_hex(i.wrapping_mul(2_654_435_761).wrapping_add(seed) & 0x00FF_FFFF))
+            .collect();
+        Texture::new(64, 64, data)
     }
 
-    for row in 0..maze.len() {
-        for col in 0..maze[row].len() {
-            if maze[row][col] == 'e' {
-                maze[row][col] = ' ';
+    #[test]
+    fn parallel_render_matches_serial_pixel_for_pixel() {
+        let maze: Vec<Vec<char>> = [
+            "+--+--+--+",
+            "|        |",
+            "|  +!+   /",
+            "|        |",
+            "+--+--+--+",
+        ]
+        .iter()
+        .map(|row| row.chars().collect())
+        .collect();
+        let textures = [test_texture(1), test_texture(2), test_texture(3)];
+        let block_size = 50.0;
+
+        for (x, y, a) in [(75.0, 75.0, 0.3), (210.0, 170.0, 2.5), (400.0, 125.0, 4.0)] {
+            let player = Player::new(x, y, a, PI / 3.0);
+
+            let mut expected = Framebuffer::new(97, 61);
+            let mut expected_heights = Vec::new();
+            render3d_serial(&mut expected, &player, &maze, block_size, &textures[0], &textures[1], &textures[2], &mut expected_heights);
+
+            for threads in [1, 3, 8] {
+                let mut actual = Framebuffer::new(97, 61);
+                let mut heights = Vec::new();
+                // La salida usa la textura de la puerta, como en el renderizador original
+                render_walls(&mut actual, &player, &maze, block_size, [&textures[0], &textures[1], &textures[2], &textures[2]], &Doors::new(&maze), &mut heights, threads);
+
+                let differing = expected.get_buffer().iter().zip(actual.get_buffer()).filter(|(a, b)| a != b).count();
+                assert_eq!(differing, 0, "{} pixels differ with {} threads", differing, threads);
+                assert_eq!(heights, expected_heights);
             }
         }
     }
 
-    for enemy in &mut *enemies {
-        let enemy_pos_x = (enemy.get_pos().x / block_size as f32) as usize;
-        let enemy_pos_y = (enemy.get_pos().y / block_size as f32) as usize;
-    
-        // Asegurarse de que las coordenadas estén dentro del rango
-        if enemy_pos_y < maze.len() && enemy_pos_x < maze[enemy_pos_y].len() && maze[enemy_pos_y][enemy_pos_x] != 'p' {
-            maze[enemy_pos_y][enemy_pos_x] = 'e';
-        }
+    #[test]
+    fn nearer_sprites_are_drawn_over_farther_ones() {
+        let maze: Vec<Vec<char>> = [
+            "+--+--+--+--+",
+            "|           |",
+            "|           |",
+            "|           |",
+            "+--+--+--+--+",
+        ]
+        .iter()
+        .map(|row| row.chars().collect())
+        .collect();
+        let textures = [test_texture(1), test_texture(2), test_texture(3)];
+        let red = Texture::new(4, 4, vec![Color::new(255, 0, 0); 16]);
+        let blue = Texture::new(4, 4, vec![Color::new(0, 0, 255); 16]);
+        let player = Player::new(75.0, 125.0, 0.0, PI / 3.0);
+
+        // El más cercano va primero en la lista, como un guardia antes que una llave
+        let sprites = [
+            PlacedSprite { pos: Vec2::new(175.0, 125.0), frame: SpriteFrame::whole(&red) },
+            PlacedSprite { pos: Vec2::new(275.0, 125.0), frame: SpriteFrame::whole(&blue) },
+        ];
+        let mut view = Framebuffer::new(160, 120);
+        render_view(&mut view, &player, &maze, 50.0, [&textures[0], &textures[1], &textures[2], &textures[2]], &Doors::new(&maze), &sprites);
+
+        let buffer = view.get_buffer();
+        assert!(buffer.contains(&0x00FF_0000));
+        assert!(!buffer.contains(&0x0000_00FF));
     }
 
-    maze
+    #[test]
+    fn full_screen_map_larger_than_the_framebuffer_starts_at_the_corner() {
+        let maze = vec![vec![' '; 40]; 30];
+        let transform = MapTransform::full_screen(&Framebuffer::new(20, 10), &maze, 50.0, 4);
+
+        assert_eq!(transform.cell_size, 1);
+        assert_eq!((transform.origin_x, transform.origin_y), (0, 0));
+    }
 }
@@ -0,0 +1,72 @@
+use crate::enemy::Enemy;
+
+use nalgebra_glm::Vec2;
+use std::collections::HashMap;
+
+// Identificador de una entidad que ocupa una celda del laberinto
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EntityId {
+    Player,
+    Enemy(usize),
+}
+
+// Índice espacial de entidades por celda. El mapa de tiles nunca se modifica;
+// las posiciones dinámicas viven aquí y se reconstruyen cada frame.
+pub struct Occupancy {
+    block_size: f32,
+    cells: HashMap<(usize, usize), Vec<EntityId>>,
+    positions: HashMap<EntityId, Vec2>,
+}
+
+impl Occupancy {
+    pub fn new(block_size: f32) -> Self {
+        Occupancy {
+            block_size,
+            cells: HashMap::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    // Convierte una posición del mundo en (columna, fila)
+    pub fn cell_of(&self, pos: Vec2) -> (usize, usize) {
+        (
+            (pos.x / self.block_size).max(0.0) as usize,
+            (pos.y / self.block_size).max(0.0) as usize,
+        )
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.positions.clear();
+    }
+
+    pub fn insert(&mut self, id: EntityId, pos: Vec2) {
+        let cell = self.cell_of(pos);
+        self.cells.entry(cell).or_default().push(id);
+        self.positions.insert(id, pos);
+    }
+
+    pub fn rebuild(&mut self, player_pos: Vec2, enemies: &[Enemy]) {
+        self.clear();
+        self.insert(EntityId::Player, player_pos);
+        for (i, enemy) in enemies.iter().enumerate() {
+            self.insert(EntityId::Enemy(i), enemy.get_pos());
+        }
+    }
+
+    pub fn at(&self, col: usize, row: usize) -> &[EntityId] {
+        self.cells.get(&(col, row)).map(|ids| ids.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn has_player(&self, col: usize, row: usize) -> bool {
+        self.at(col, row).contains(&EntityId::Player)
+    }
+
+    pub fn position(&self, id: EntityId) -> Option<Vec2> {
+        self.positions.get(&id).copied()
+    }
+
+    pub fn player_cell(&self) -> Option<(usize, usize)> {
+        self.position(EntityId::Player).map(|pos| self.cell_of(pos))
+    }
+}
@@ -1,7 +1,6 @@
 use nalgebra_glm::Vec2;
 use minifb::{Window, Key};
 use std::f32::consts::PI;
-use crate::AudioPlayer;
 use crate::maze::is_wall;
 use crate::Framebuffer;
 use crate::Color;
@@ -13,8 +12,22 @@ pub struct Player {
     prev_mouse_x: f32,
     prev_mouse_y: f32,
     mouse_sensitivity: f32, // Sensibilidad del ratón
+    pub sprinting: bool, // Shift pulsado mientras se mueve
+    pub stamina: f32, // 0.0 - 1.0; correr la gasta
+    exhausted: bool,
+    pub items: Vec<String>, // Objetos recogidos en el nivel
 }
 
+// Multiplicador de velocidad al correr
+const SPRINT_MULTIPLIER: f32 = 1.6;
+
+// Aguante gastado y recuperado por frame: unos 3 segundos corriendo y 5 para
+// recuperarse del todo. Tras agotarse no se puede correr hasta recuperar
+// `STAMINA_RECOVERED`.
+const STAMINA_DRAIN: f32 = 1.0 / 180.0;
+const STAMINA_RECOVERY: f32 = 1.0 / 300.0;
+const STAMINA_RECOVERED: f32 = 0.3;
+
 impl Player {
     pub fn new(x: f32, y: f32, a: f32, fov: f32) -> Self {
         Player {
@@ -24,9 +37,17 @@ impl Player {
             prev_mouse_x: 0.0,
             prev_mouse_y: 0.0,
             mouse_sensitivity: 0.01, // Ajusta la sensibilidad del ratón según sea necesario
+            sprinting: false,
+            stamina: 1.0,
+            exhausted: false,
+            items: Vec::new(),
         }
     }
 
+    pub fn set_mouse_sensitivity(&mut self, sensitivity: f32) {
+        self.mouse_sensitivity = sensitivity;
+    }
+
     pub fn get_pos(&mut self) -> Vec2 {
         self.pos
     } 
@@ -35,10 +56,15 @@ impl Player {
         self.a
     }
 
-    pub fn process_events(&mut self, window: &Window, maze: &Vec<Vec<char>>, block_size: f32, framebuffer: &mut Framebuffer, audio: &mut AudioPlayer) -> (String, Vec2) {
-        const MOVE_SPEED: f32 = 2.3;
+    pub fn process_events(&mut self, window: &Window, maze: &Vec<Vec<char>>, block_size: f32, framebuffer: &mut Framebuffer) -> (String, Vec2) {
+        const WALK_SPEED: f32 = 2.3;
         const ROTATION_SPEED: f32 = std::f32::consts::PI / 30.0;
         let mut key_down = String::new();
+
+        // Correr con Shift mientras quede aguante
+        let sprint_held = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+        self.sprinting = sprint_held && !self.exhausted;
+        let move_speed = if self.sprinting { WALK_SPEED * SPRINT_MULTIPLIER } else { WALK_SPEED };
         
         let cos_a = self.a.cos();
         let sin_a = self.a.sin();
@@ -56,34 +82,30 @@ impl Player {
     
         // Movimiento hacia adelante y hacia atrás (W y S)
         if window.is_key_down(Key::Up) || window.is_key_down(Key::W) {
-            audio.play();
-            move_x += MOVE_SPEED * cos_a;
-            move_y += MOVE_SPEED * sin_a;
+            move_x += move_speed * cos_a;
+            move_y += move_speed * sin_a;
             key_down.push('w');
         }
         if window.is_key_down(Key::Down) || window.is_key_down(Key::S) {
-            audio.play();
-            move_x -= MOVE_SPEED * cos_a;
-            move_y -= MOVE_SPEED * sin_a;
+            move_x -= move_speed * cos_a;
+            move_y -= move_speed * sin_a;
             key_down.push('s');
         }
     
         // Movimiento lateral (A y D)
         if window.is_key_down(Key::A) {
-            audio.play();
-            move_x += MOVE_SPEED * sin_a;
-            move_y -= MOVE_SPEED * cos_a;
+            move_x += move_speed * sin_a;
+            move_y -= move_speed * cos_a;
             key_down.push('a');
         }
         if window.is_key_down(Key::D) {
-            audio.play();
-            move_x -= MOVE_SPEED * sin_a;
-            move_y += MOVE_SPEED * cos_a;
+            move_x -= move_speed * sin_a;
+            move_y += move_speed * cos_a;
             key_down.push('d');
         }
     
         // Normalizar movimiento en diagonal
-        let diagonal_speed = MOVE_SPEED / (2.0f32).sqrt();
+        let diagonal_speed = move_speed / (2.0f32).sqrt();
         if (window.is_key_down(Key::W) || window.is_key_down(Key::Up)) && window.is_key_down(Key::A) {
             move_x = diagonal_speed * (cos_a + sin_a);
             move_y = diagonal_speed * (sin_a - cos_a);
@@ -107,6 +129,10 @@ impl Player {
     
         let new_pos = Vec2::new(self.pos.x + move_x, self.pos.y + move_y);
         let (is_collision, char_wall) = is_wall(maze, (new_pos.x / block_size as f32) as usize, (new_pos.y / block_size as f32) as usize);
+
+        // Solo gasta aguante si la posición cambia de verdad, no al correr contra una pared
+        self.update_stamina(!is_collision && new_pos != self.pos);
+
         if !is_collision {
             self.pos = new_pos;
             return (key_down, self.pos);
@@ -120,6 +146,21 @@ impl Player {
     }
     
 
+    // Gasta aguante si corre mientras se desplaza y lo recupera en otro caso
+    fn update_stamina(&mut self, moving: bool) {
+        if self.sprinting && moving {
+            self.stamina = (self.stamina - STAMINA_DRAIN).max(0.0);
+            if self.stamina == 0.0 {
+                self.exhausted = true;
+            }
+        } else {
+            self.stamina = (self.stamina + STAMINA_RECOVERY).min(1.0);
+            if self.stamina >= STAMINA_RECOVERED {
+                self.exhausted = false;
+            }
+        }
+    }
+
     pub fn update_mouse(&mut self, mouse_x: f32, mouse_y: f32, window_width: f32, window_height: f32) {
         // Calcula el movimiento del ratón
         let delta_x = mouse_x - self.prev_mouse_x;
@@ -151,4 +192,33 @@ impl Player {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprinting_drains_stamina_until_exhausted() {
+        let mut player = Player::new(0.0, 0.0, 0.0, 1.0);
+
+        let mut frames = 0;
+        while !player.exhausted {
+            player.sprinting = !player.exhausted;
+            player.update_stamina(true);
+            frames += 1;
+        }
+        assert!((179..=181).contains(&frames));
+
+        // Sin aguante no vuelve a correr hasta recuperar una parte: 90 frames
+        player.sprinting = false;
+        for _ in 0..80 {
+            player.update_stamina(true);
+        }
+        assert!(player.exhausted);
+        for _ in 0..20 {
+            player.update_stamina(true);
+        }
+        assert!(!player.exhausted);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::assets::Sound;
+use crate::audio_engine::{AudioEngine, Bus, Emitter};
+use crate::cast_ray::cast_ray;
+use crate::enemy::Enemy;
+use crate::player::Player;
+use crate::rng::next_random;
+
+use nalgebra_glm::Vec2;
+use std::time::{Duration, Instant};
+
+// Distancia máxima (en bloques) a la que se oye a un guardia
+const HEARING_RANGE_BLOCKS: f32 = 6.0;
+
+// Distancia recorrida por un guardia entre dos pasos, en unidades del mundo
+const GUARD_STRIDE: f32 = 40.0;
+
+// Atenuación extra cuando hay una pared de por medio
+const MUFFLED_GAIN: f32 = 0.5;
+
+// Calcula panorama, atenuación y oclusión de un sonido en `source` según la
+// posición y dirección de vista del jugador
+pub fn spatialize(player: &Player, source: Vec2, maze: &Vec<Vec<char>>, block_size: f32) -> Emitter {
+    let max_distance = HEARING_RANGE_BLOCKS * block_size;
+    let offset = source - player.pos;
+    let distance = offset.norm();
+
+    if distance >= max_distance {
+        return Emitter { pan: 0.0, gain: 0.0, muffled: false };
+    }
+
+    // Vector a la derecha de la vista (y crece hacia abajo en el mundo)
+    let right = Vec2::new(-player.a.sin(), player.a.cos());
+    let pan = if distance > f32::EPSILON { offset.dot(&right) / distance } else { 0.0 };
+
+    let falloff = 1.0 - distance / max_distance;
+    let mut gain = falloff * falloff;
+
+    let angle = offset.y.atan2(offset.x);
+    let muffled = match cast_ray(&player.pos, angle, maze, block_size, false, distance, None) {
+        Some(intersect) => intersect.distance < distance,
+        None => false,
+    };
+    if muffled {
+        gain *= MUFFLED_GAIN;
+    }
+
+    Emitter { pan, gain, muffled }
+}
+
+// Sonidos que emiten los guardias: pasos según la distancia que recorren y
+// charla de radio a intervalos irregulares
+pub struct GuardSounds {
+    last_pos: Vec<Vec2>,
+    travelled: Vec<f32>,
+    next_chatter: Vec<Instant>,
+    seed: u32,
+}
+
+impl GuardSounds {
+    pub fn new(enemies: &[Enemy]) -> Self {
+        let mut sounds = GuardSounds {
+            last_pos: enemies.iter().map(|enemy| enemy.get_pos()).collect(),
+            travelled: vec![0.0; enemies.len()],
+            next_chatter: Vec::with_capacity(enemies.len()),
+            seed: 0x9E37_79B9,
+        };
+        for _ in enemies {
+            let delay = sounds.chatter_delay();
+            sounds.next_chatter.push(Instant::now() + delay);
+        }
+        sounds
+    }
+
+    fn chatter_delay(&mut self) -> Duration {
+        Duration::from_secs_f32(6.0 + 8.0 * next_random(&mut self.seed))
+    }
+
+    pub fn update(
+        &mut self,
+        enemies: &[Enemy],
+        player: &Player,
+        maze: &Vec<Vec<char>>,
+        block_size: f32,
+        audio: &mut AudioEngine,
+        footsteps: &Sound,
+        chatter: &Sound,
+    ) {
+        let now = Instant::now();
+
+        for (i, enemy) in enemies.iter().enumerate().take(self.last_pos.len()) {
+            let pos = enemy.get_pos();
+            self.travelled[i] += (pos - self.last_pos[i]).norm();
+            self.last_pos[i] = pos;
+
+            let step = self.travelled[i] >= GUARD_STRIDE;
+            let talk = now >= self.next_chatter[i];
+            if !step && !talk {
+                continue;
+            }
+
+            let emitter = spatialize(player, pos, maze, block_size);
+            if step {
+                self.travelled[i] -= GUARD_STRIDE;
+                audio.play_positional(footsteps, Bus::Sfx, 0.3, &emitter);
+            }
+            if talk {
+                self.next_chatter[i] = now + self.chatter_delay();
+                audio.play_positional(chatter, Bus::Sfx, 0.6, &emitter);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_backend::NullBackend;
+
+    fn open_room() -> Vec<Vec<char>> {
+        let mut maze = vec![vec![' '; 8]; 8];
+        for i in 0..8 {
+            maze[0][i] = '+';
+            maze[7][i] = '+';
+            maze[i][0] = '|';
+            maze[i][7] = '|';
+        }
+        maze
+    }
+
+    #[test]
+    fn walking_guard_plays_footsteps() {
+        let maze = open_room();
+        let block_size = 100.0;
+        let backend = NullBackend::new();
+        let log = backend.log();
+        let mut audio = AudioEngine::new(Box::new(backend));
+
+        let player = Player::new(150.0, 150.0, 0.0, std::f32::consts::FRAC_PI_3);
+        let mut enemies = vec![Enemy::new(Vec2::new(250.0, 150.0), 0.0, 10.0, 22.5, 100.0)];
+        let mut sounds = GuardSounds::new(&enemies);
+        let footsteps = Sound::silence("Audio/Footsteps.wav");
+        let chatter = Sound::silence("Audio/Radio.wav");
+
+        for _ in 0..10 {
+            let pos = enemies[0].get_pos();
+            enemies[0].set_pos(pos + Vec2::new(10.0, 0.0));
+            sounds.update(&enemies, &player, &maze, block_size, &mut audio, &footsteps, &chatter);
+        }
+
+        assert_eq!(log.count_played("Audio/Footsteps.wav"), 100 / GUARD_STRIDE as usize);
+    }
+
+    #[test]
+    fn radio_chatter_sample_is_embedded() {
+        use crate::asset_pack::AssetPack;
+        use crate::assets::Assets;
+        use rodio::Source;
+
+        let radio = Assets::new(AssetPack::new(None)).sound("Audio/Radio.wav");
+        assert!(radio.source().total_duration().unwrap() > Duration::from_millis(500));
+    }
+}
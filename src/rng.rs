@@ -0,0 +1,8 @@
+// Generador pseudoaleatorio mínimo (xorshift) para espaciar la charla de radio
+// y variar los pasos. Devuelve un valor en [0, 1).
+pub fn next_random(seed: &mut u32) -> f32 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 17;
+    *seed ^= *seed << 5;
+    (*seed % 10_000) as f32 / 10_000.0
+}
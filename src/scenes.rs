@@ -1,16 +1,29 @@
-use crate::AudioPlayer;
+use crate::audio_engine::{AudioEngine, Bus};
 use crate::Framebuffer;
+use crate::framebuffer::Scaling;
 use crate::Color;
 use crate::polygon::Polygon;
-use crate::maze::{render, render3d, render_enemies_pos, render_enemy, draw_player_position, draw_enemies_position, draw_enemy_fov, minimap};
-use crate::texture::Texture;
+use crate::maze::{render, render_view, render_enemies_pos, draw_player_position, draw_enemies_position, draw_enemy_fov, draw_full_map, minimap, rotating_minimap, MapTransform, MinimapMode};
+use crate::assets::{Assets, AssetError, AssetRequest};
 use crate::player::Player;
 use crate::enemy::Enemy;
+use crate::occupancy::Occupancy;
+use crate::exploration::Exploration;
+use crate::positional_audio::GuardSounds;
+use crate::footsteps::{Footsteps, FloorMap};
+use crate::layout::{Anchor, Layout};
+use crate::layer::CachedLayer;
+use crate::glyph_cache::CachedFont;
+use crate::hud::{Hud, HudState};
+use crate::capture::save_screenshot;
+use crate::sprite::{Clip, PlacedSprite, SpriteFrame, SpriteSheet, GUARD_SHEET};
+use crate::doors::{collect_pickups, find_pickups, load_exit_texture, pickup_texture, Doors, Lock};
+use crate::text::{draw_line, draw_text_box, Align, TextStyle, UiFont};
+use crate::settings::{Settings, RESOLUTIONS, RENDER_SCALES, MIN_FOV, MAX_FOV, MIN_SENSITIVITY, MAX_SENSITIVITY};
 
 
 use std::time::{Duration, Instant};
-use minifb::{Window, Key};
-use image::GenericImageView;
+use minifb::{Window, Key, KeyRepeat};
 use nalgebra_glm::Vec2;
 use std::f32::consts::PI;
 
@@ -20,37 +33,95 @@ fn calculate_fps(start_time: Instant, frame_count: usize) -> f64 {
     frame_count as f64 / duration
 }
 
-pub fn game_start(width: usize, height: usize, framebuffer: &mut Framebuffer, window: &mut Window) {
-    let mut audio = AudioPlayer::new("Audio/Inicio.mp3", 0.5);
-    
-    audio.play();
+// Archivos de los niveles, en el orden de la selección de nivel
+pub const LEVELS: [&str; 3] = ["src/maze1.txt", "src/maze2.txt", "src/maze3.txt"];
+
+// Duración de los fundidos cruzados de la música entre pantallas
+const MUSIC_FADE: Duration = Duration::from_millis(800);
+
+// Sonidos de los menús: mover la selección o cambiar un ajuste, y aceptar
+const UI_SELECT: &str = "Audio/Select.wav";
+const UI_CONFIRM: &str = "Audio/Confirm.wav";
+const UI_VOLUME: f32 = 0.5;
+
+// Reproduce un sonido de la interfaz en el bus `Ui`
+fn play_ui_sound(audio: &mut AudioEngine, assets: &mut Assets, path: &str) {
+    audio.play(&assets.sound(path), Bus::Ui, UI_VOLUME);
+}
+
+// Dibuja `text` anclado a la pantalla; el tamaño del estilo y los
+// desplazamientos están en píxeles de la resolución de referencia
+fn draw_label(framebuffer: &mut Framebuffer, font: &CachedFont, anchor: Anchor, offset_x: f32, offset_y: f32, text: &str, style: TextStyle) {
+    let layout = Layout::for_framebuffer(framebuffer);
+    let style = style.with_size(layout.size(style.size)).aligned(Align::Left);
+    let (text_width, text_height) = Framebuffer::text_size(font, text, style.size);
+    let (x, y) = layout.place(anchor, offset_x, offset_y, text_width, text_height);
+    draw_line(framebuffer, font, x as isize, y as isize, text, &style);
+}
+
+// Muestra el frame; con F12 guarda además una captura de pantalla
+fn present_frame(framebuffer: &mut Framebuffer, window: &mut Window) {
+    if window.is_key_pressed(Key::F12, KeyRepeat::No) {
+        match save_screenshot(framebuffer) {
+            Ok(path) => println!("saved screenshot to '{}'", path.display()),
+            Err(e) => eprintln!("warning: could not save screenshot: {}", e),
+        }
+    }
+    framebuffer.present(window);
+}
+
+pub fn game_start(width: usize, height: usize, framebuffer: &mut Framebuffer, window: &mut Window, assets: &mut Assets, audio: &mut AudioEngine, settings: &mut Settings) -> Result<(), AssetError> {
+    audio.play_music("title", &assets.sound("Audio/Inicio.mp3"), MUSIC_FADE);
 
-    let begin_page = "textures/Inicio.png";
+    let begin_page = assets.width_fitted_image("textures/Inicio.png", width);
+    let mut font = assets.font(settings.font.path())?;
     
     let blink_interval = Duration::from_millis(200);
     let mut last_blink_time = Instant::now();
     let mut show_text = true;
     let mut enter_pressed = false;
 
+    // La imagen y la ayuda fija se dibujan una vez; solo el texto que parpadea
+    // se dibuja en cada frame
+    let mut background = CachedLayer::new();
+
     while window.is_open() && !enter_pressed && !window.is_key_down(minifb::Key::Escape) {
         framebuffer.clear();
-        framebuffer.draw_image(&begin_page, width, height);
-        
+        let layer = background.get(framebuffer.get_width(), framebuffer.get_height(), 0, |layer| {
+            layer.draw_image(&begin_page);
+            draw_label(layer, &font, Anchor::Bottom, 0.0, 60.0, "Press S for settings", TextStyle::new(40.0, Color::new(200, 200, 200)).with_shadow(Color::new(0, 0, 0)));
+        });
+        framebuffer.composite(layer, 0, 0, 1.0);
+
         if last_blink_time.elapsed() >= blink_interval {
             show_text = !show_text;
             last_blink_time = Instant::now();
         }
 
         if show_text {
-            framebuffer.draw_text(width / 5, (4 * height) / 5 - 25, "Press ENTER to start game", Color::new(255, 255, 255), 70.0);
+            draw_label(framebuffer, &font, Anchor::Bottom, 0.0, 115.0, "Press ENTER to start game", TextStyle::new(70.0, Color::new(255, 255, 255)).with_shadow(Color::new(0, 0, 0)));
+        }
+
+        // Menú de ajustes; si cambió la resolución se vuelve a crear el framebuffer
+        if window.is_key_pressed(Key::S, KeyRepeat::No) {
+            play_ui_sound(audio, assets, UI_CONFIRM);
+            if settings_screen(framebuffer, window, assets, audio, settings)? {
+                resize_framebuffer(framebuffer, settings);
+                return game_start(settings.width, settings.height, framebuffer, window, assets, audio, settings);
+            }
+            // La fuente pudo cambiar en el menú
+            font = assets.font(settings.font.path())?;
+            background.invalidate();
         }
 
         // Detectar cuando Enter se presiona por primera vez
         if window.is_key_down(minifb::Key::Enter) {
+            play_ui_sound(audio, assets, UI_CONFIRM);
             enter_pressed = true;
         }
 
-        window.update_with_buffer(&framebuffer.get_buffer(), width, height).unwrap();
+        audio.update();
+        present_frame(framebuffer, window);
         std::thread::sleep(Duration::from_millis(16));
     }
 
@@ -63,104 +134,129 @@ pub fn game_start(width: usize, height: usize, framebuffer: &mut Framebuffer, wi
             enter_pressed = false;
         }
 
-        window.update_with_buffer(&framebuffer.get_buffer(), width, height).unwrap();
+        audio.update();
+        present_frame(framebuffer, window);
         std::thread::sleep(Duration::from_millis(16));
     }
 
-    audio.stop();
-
-    level_selector(framebuffer, window, width, height);
+    level_selector(framebuffer, window, width, height, assets, audio, settings)
 }
 
-fn level_selector(framebuffer: &mut Framebuffer, window: &mut Window, width: usize, height: usize) {
+fn level_selector(framebuffer: &mut Framebuffer, window: &mut Window, width: usize, height: usize, assets: &mut Assets, audio: &mut AudioEngine, settings: &mut Settings) -> Result<(), AssetError> {
     let mut option = 0;
 
-    // Definición de los polígonos de fondo para cada nivel
-    let first_level_background: Vec<[isize; 2]> = vec![
-        [0, 0],
-        [0, height as isize],
-        [width as isize / 3, height as isize],
-        [width as isize / 3, 0],
+    let font = assets.font(settings.font.path())?;
+    let level_images = [
+        assets.scaled_image("textures/prison1.jpeg", width / 4, height / 4),
+        assets.scaled_image("textures/prison2.jpg", width / 4, height / 4),
+        assets.scaled_image("textures/prison3.jpg", width / 4, height / 4),
     ];
 
-    let second_level_background: Vec<[isize; 2]> = vec![
-        [width as isize / 3, 0],
-        [width as isize / 3, height as isize],
-        [2 * (width as isize) / 3, height as isize],
-        [2 * (width as isize) / 3, 0],
-    ];
-
-    let third_level_background: Vec<[isize; 2]> = vec![
-        [2 * (width as isize) / 3, 0],
-        [2 * (width as isize) / 3, height as isize],
-        [width as isize, height as isize],
-        [width as isize, 0],
-    ];
+    // Textos e imágenes, que no cambian con la selección
+    let mut labels = CachedLayer::new();
 
     // Bucle principal de la selección de nivel
     while window.is_open() && !window.is_key_down(minifb::Key::Escape) {
         framebuffer.clear();
 
-        // Dibujar el fondo según la opción seleccionada
-        match option {
-            0 => framebuffer.polygon(&first_level_background, Color::new(128, 128, 128), Color::new(128, 128, 128)),
-            1 => framebuffer.polygon(&second_level_background, Color::new(128, 128, 128), Color::new(128, 128, 128)),
-            2 => framebuffer.polygon(&third_level_background, Color::new(128, 128, 128), Color::new(128, 128, 128)),
-            _ => println!("Invalid option"),
-        }
+        // Dibujar el fondo de la columna seleccionada
+        let column_left = option * width / 3;
+        let column_width = (option + 1) * width / 3 - column_left;
+        framebuffer.fill_rect(column_left as isize, 0, column_width + 1, height, Color::new(128, 128, 128));
 
         // Cambiar opción con las teclas de flecha
         if window.is_key_down(minifb::Key::Left) && option > 0 {
             option -= 1;
+            play_ui_sound(audio, assets, UI_SELECT);
         } else if window.is_key_down(minifb::Key::Right) && option < 2 {
             option += 1;
+            play_ui_sound(audio, assets, UI_SELECT);
         }
 
-        // Dibujar los textos y las imágenes
-        framebuffer.draw_text(width / 24 + 40, height / 5, "Level 1", Color::new(255, 255, 255), 60.0);
-        framebuffer.draw_image_at_position("textures/prison1.jpeg", width / 4, height / 4, width / 24 - 1, 2 * height / 5);
-
-        framebuffer.draw_text(2 * width / 5 + 30, height / 5, "Level 2", Color::new(255, 255, 255), 60.0);
-        framebuffer.draw_image_at_position("textures/prison2.jpg", width / 4, height / 4, 2 * width / 5 - 25, 2 * height / 5);
-
-        framebuffer.draw_text(4 * width / 6 + 85, height / 5, "Level 3", Color::new(255, 255, 255), 60.0);
-        framebuffer.draw_image_at_position("textures/prison3.jpg", width / 4, height / 4, 4 * width / 6 + 45, 2 * height / 5);
+        // Dibujar los textos y las imágenes centrados en su columna
+        let layer = labels.get(width, height, 0, |layer| {
+            let layout = Layout::for_framebuffer(layer);
+            let label_style = TextStyle::new(layout.size(60.0), Color::new(255, 255, 255)).aligned(Align::Center);
+            let (_, label_y) = layout.place(Anchor::Top, 0.0, 160.0, 0, 0);
+            for (i, image) in level_images.iter().enumerate() {
+                let column_left = i * width / 3;
+                let column_width = (i + 1) * width / 3 - column_left;
+                draw_text_box(layer, &font, column_left as isize, label_y as isize, column_width, &format!("Level {}", i + 1), &label_style);
+                layer.draw_image_at_position(image, (column_left + column_width / 2).saturating_sub(image.width / 2), 2 * height / 5);
+            }
 
-        framebuffer.draw_text(width / 3 + 5, height / 30, "Level selector", Color::new(255, 255, 255), 60.0);
+            draw_label(layer, &font, Anchor::Top, 0.0, 27.0, "Level selector", TextStyle::new(60.0, Color::new(255, 255, 255)));
+        });
+        framebuffer.composite(layer, 0, 0, 1.0);
 
         // Salir del ciclo si se presiona Enter
         if window.is_key_down(minifb::Key::Enter) {
+            play_ui_sound(audio, assets, UI_CONFIRM);
             break;
         }
 
         // Actualizar la ventana con el contenido del framebuffer
-        window.update_with_buffer(&framebuffer.get_buffer(), width, height).unwrap();
+        audio.update();
+        present_frame(framebuffer, window);
         std::thread::sleep(Duration::from_millis(16));
     }
 
-    let mut file_path = "";
+    let file_path = LEVELS[option];
+
+    controls_screen(framebuffer, file_path, window, option, assets, audio, settings)
+}
 
-    match option {
-        0 => file_path = "src/maze1.txt",
-        1 => file_path = "src/maze2.txt",
-        2 => file_path = "src/maze3.txt",
-        _ => file_path = "src/maze1.txt",
-    }
 
-    controls_screen(framebuffer, file_path, width, height, window,option);
+// Celda (columna, fila) que el jugador tiene delante, al alcance de la mano
+fn facing_cell(player: &Player, block_size: f32) -> Option<(usize, usize)> {
+    let ahead = player.pos + Vec2::new(player.a.cos(), player.a.sin()) * block_size * 0.75;
+    if ahead.x < 0.0 || ahead.y < 0.0 {
+        return None;
+    }
+    Some(((ahead.x / block_size) as usize, (ahead.y / block_size) as usize))
 }
 
+// Indicación del HUD según la celda que el jugador tiene delante
+fn hud_prompt(maze: &Vec<Vec<char>>, doors: &Doors, player: &Player, block_size: f32) -> Option<&'static str> {
+    let (col, row) = facing_cell(player, block_size)?;
+    if let Some(prompt) = doors.prompt(col, row, &player.items) {
+        return Some(prompt);
+    }
+    match maze.get(row).and_then(|line| line.get(col)) {
+        Some('/') => Some("Walk through the exit to escape"),
+        Some('!') => Some("This cell door is locked"),
+        _ => None,
+    }
+}
 
-fn gameplay(framebuffer: &mut Framebuffer, file_path: &str, width: usize, height: usize, window: &mut Window, map_chosen: usize) {
-    let (mut maze, player_pos) = render(framebuffer, file_path, 0.5);
+// Recursos que usa un nivel; se precargan detrás de la pantalla de carga
+const LEVEL_ASSETS: [AssetRequest<'static>; 9] = [
+    AssetRequest::Texture("textures/prison_wall.png"),
+    AssetRequest::Texture("textures/Cell.png"),
+    AssetRequest::Texture("textures/Door.jpeg"),
+    AssetRequest::Texture(GUARD_SHEET),
+    AssetRequest::Sound("Audio/Footsteps.wav"),
+    AssetRequest::Sound("Audio/Juego.mp3"),
+    AssetRequest::Sound("Audio/Radio.wav"),
+    AssetRequest::Font(UiFont::Pixel.path()),
+    AssetRequest::Font(UiFont::Meditative.path()),
+];
+
+fn gameplay(framebuffer: &mut Framebuffer, file_path: &str, window: &mut Window, map_chosen: usize, assets: &mut Assets, audio: &mut AudioEngine, settings: &mut Settings) -> Result<(), AssetError> {
+    let (width, height) = (settings.width, settings.height);
+    let level = assets.maze(file_path)?;
+    let floor = FloorMap::new(&assets.floor(file_path)?);
+    let (mut maze, player_pos) = render(framebuffer, &level, 0.5);
     let mut key_down = String::new(); // Cambiado a String
 
-    let enemies_pos = render_enemies_pos(framebuffer, file_path);
+    let enemies_pos = render_enemies_pos(framebuffer, &level);
     let block_size = std::cmp::min(
         framebuffer.get_width() / maze[0].len(),
         framebuffer.get_height() / maze.len(),
     ) as f32;
     
+    let mut doors = Doors::new(&maze);
+    let mut pickups = find_pickups(&level, block_size);
     let mut enemies: Vec<Enemy> = Vec::new();
 
     let mut direc_enemies: Vec<f32> = Vec::new();
@@ -174,131 +270,211 @@ fn gameplay(framebuffer: &mut Framebuffer, file_path: &str, width: usize, height
 
     for (pos, dir) in enemies_pos.iter().zip(direc_enemies.iter()) {
         if *dir == 2.0*PI/2.0 {
-            enemies.push(Enemy::new(*pos, *dir, 55.0, 20.0, framebuffer.get_height() as f32));
+            enemies.push(Enemy::new(*pos, *dir, 55.0, 22.5, 100.0));
         } else {
-            enemies.push(Enemy::new(*pos, *dir, -55.0, 20.0, framebuffer.get_height() as f32));
+            enemies.push(Enemy::new(*pos, *dir, -55.0, 22.5, 100.0));
         }
     }
 
 
-    let mut player = Player::new(player_pos.x, player_pos.y, 0.0, PI / 3.0);
-    let mut enemy_in_map = false;
+    let mut player = Player::new(player_pos.x, player_pos.y, 0.0, settings.fov_radians());
+    player.set_mouse_sensitivity(settings.mouse_sensitivity);
 
-    let mut og_pos = player.get_pos();
-    let mut new_pos = player.get_pos();
+    let mut occupancy = Occupancy::new(block_size);
+    let mut exploration = Exploration::new(&maze, enemies.len());
+    let mut show_full_map = false;
+    let mut minimap_mode = MinimapMode::Fixed;
 
-    let texture = Texture::from_file("textures/prison_wall.png");
-    let texture_cell = Texture::from_file("textures/Cell.png");
-    let texture_door = Texture::from_file("textures/Door.jpeg");
-    let enemy_texture = Texture::from_file("textures/Police.png");
+    // Capas del minimapa: la fija se reutiliza mientras no se exploren celdas
+    // nuevas; la giratoria se vuelve a dibujar cada frame
+    let minimap_radius = std::cmp::min(framebuffer.get_width(), framebuffer.get_height()) / 8;
+    let mut minimap_layer = CachedLayer::new();
+    let mut rotating_layer = Framebuffer::new_transparent(2 * minimap_radius + 1, 2 * minimap_radius + 1);
+
+    let texture = assets.texture("textures/prison_wall.png");
+    let texture_cell = assets.texture("textures/Cell.png");
+    let texture_door = assets.texture("textures/Door.jpeg");
+    let texture_exit = load_exit_texture(assets, &texture_door);
+    let guard_sheet = SpriteSheet::guards(assets);
+    let key_texture = pickup_texture(Lock::Key);
+    let keycard_texture = pickup_texture(Lock::Keycard);
+    let font = assets.font(settings.font.path())?;
 
     let mut frame_count = 0;
     let start_time = Instant::now();
 
-    // Inicializa el z_buffer
-    let mut z_buffer = vec![f32::INFINITY; framebuffer.get_width()];
+    // La vista 3D se dibuja a la escala de render y se amplía al framebuffer;
+    // el minimapa y los textos se dibujan encima a resolución completa
+    let view_width = ((width as f32 * settings.render_scale).round() as usize).max(1);
+    let view_height = ((height as f32 * settings.render_scale).round() as usize).max(1);
+    let mut view = Framebuffer::new(view_width, view_height);
 
-    let mut audio = AudioPlayer::new("Audio/Footsteps.wav", 0.1);
+    let footsteps = assets.sound("Audio/Footsteps.wav");
+    let mut player_footsteps = Footsteps::new(assets);
+    let radio_chatter = assets.sound("Audio/Radio.wav");
+    let mut guard_sounds = GuardSounds::new(&enemies);
+    audio.play_music("gameplay", &assets.sound("Audio/Juego.mp3"), MUSIC_FADE);
 
+    let mut hud = Hud::new();
     let mut enemy_collision = true;
+
+    // Reloj de la simulación para las animaciones y clip de cada guardia,
+    // elegido con lo que vio en el frame anterior
+    let mut clock = 0.0;
+    let mut guard_clips: Vec<Clip> = enemies.iter().map(|enemy| Clip::for_guard(enemy, false, false)).collect();
     
     while window.is_open() && !window.is_key_down(Key::Escape) && enemy_collision {
-        if let Some((mouse_x, mouse_y)) = window.get_mouse_pos(minifb::MouseMode::Clamp) {
-            player.update_mouse(mouse_x as f32, mouse_y as f32, width as f32, height as f32);
+        if let Some((mouse_x, mouse_y)) = framebuffer.mouse_pos(window) {
+            player.update_mouse(mouse_x, mouse_y, width as f32, height as f32);
         }
     
-        // Cambia `audio` a referencia mutable
-        let (key_down_str, new_pos) = player.process_events(&window, &maze, block_size, framebuffer, &mut audio);
+        let previous_pos = player.get_pos();
+        let (key_down_str, _) = player.process_events(&window, &maze, block_size, framebuffer);
+
+        // Pasos según la distancia recorrida y el suelo bajo el jugador
+        let moved = (player.get_pos() - previous_pos).norm();
+        player_footsteps.update(moved, player.sprinting, floor.surface_at(player.get_pos(), block_size), audio);
 
         key_down = key_down_str; // Actualiza el valor de `key_down` con el valor de `key_down_str`
 
         if key_down == "/" {
             break;
         }
+
+        // Llaves en la celda del jugador y puertas con la tecla E
+        let player_pos = player.get_pos();
+        collect_pickups(&mut pickups, player_pos, block_size, &mut player.items);
+        if window.is_key_pressed(Key::E, KeyRepeat::No) {
+            if let Some((col, row)) = facing_cell(&player, block_size) {
+                doors.interact(col, row, &player.items);
+            }
+        }
     
         framebuffer.clear();
     
-        let mut wall_heights = vec![0; framebuffer.get_width()];
-    
-        // Renderiza el mapa en 3D
-        render3d(framebuffer, &player, &maze, block_size, &texture, &texture_cell, &texture_door, &mut wall_heights);
+        // Renderiza el mapa en 3D y los enemigos
+        let mut sprites = guard_sheet.guard_sprites(&enemies, &guard_clips, player.get_pos(), clock);
+        sprites.extend(pickups.iter().map(|pickup| PlacedSprite {
+            pos: pickup.pos,
+            frame: SpriteFrame::whole(match pickup.lock {
+                Lock::Key => &key_texture,
+                Lock::Keycard => &keycard_texture,
+            }),
+        }));
+        render_view(&mut view, &player, &maze, block_size, [&texture, &texture_cell, &texture_door, &texture_exit], &doors, &sprites);
+        framebuffer.draw_scaled(&view);
     
-        // Renderiza los enemigos
-        for enemy in &enemies {
-            render_enemy(
-                framebuffer,
-                &mut player,
-                &enemy.get_pos(),
-                &mut z_buffer,
-                &enemy_texture,
-                &wall_heights,
-                150.0,
-                &maze,
-                block_size,
-            );
+        occupancy.rebuild(player.get_pos(), &enemies);
+
+        // Cambia entre el minimapa fijo y el giratorio (tecla N)
+        if window.is_key_pressed(Key::N, KeyRepeat::No) {
+            minimap_mode = minimap_mode.toggle();
         }
-    
-        (maze, enemy_in_map) = minimap(framebuffer, maze.clone(), 0.5, key_down, player.get_a(), og_pos, new_pos, &mut enemies, block_size as usize);
+
+        // El minimapa giratorio escala con el tamaño del framebuffer
+        let minimap_center = (minimap_radius + 10) as isize;
+        let minimap_transform = match minimap_mode {
+            MinimapMode::Fixed => MapTransform::corner(block_size),
+            MinimapMode::Rotating => MapTransform::rotating(minimap_center, minimap_center, minimap_radius, &player, block_size),
+        };
+
+        match minimap_mode {
+            MinimapMode::Fixed => minimap(framebuffer, &mut minimap_layer, &maze, 0.5, &occupancy, &mut exploration, &minimap_transform),
+            MinimapMode::Rotating => rotating_minimap(framebuffer, &mut rotating_layer, &maze, 0.5, &occupancy, &mut exploration, &minimap_transform, minimap_radius, player.fov),
+        };
     
         let delta_time = 1.0 / 30.0;
+        clock += delta_time;
     
         // Actualiza todos los enemigos
         for enemy in &mut enemies {
-            let check_collision = enemy.update(delta_time, &maze, block_size);
+            // Un guardia frente a una puerta la abre y espera a que se abra
+            let waiting = enemy.cell_ahead(block_size * 0.6, block_size).is_some_and(|(col, row)| doors.request_by_guard(col, row));
+            let check_collision = if waiting {
+                enemy.check_collision_with_player(&maze, &occupancy)
+            } else {
+                enemy.update(delta_time, &maze, block_size, &occupancy)
+            };
             if check_collision {
                 enemy_collision = false;
                 break;
             }
         }
+
+        doors.update(delta_time, &mut maze, |col, row| !occupancy.at(col, row).is_empty());
+
+        // Pasos y radio de los guardias, situados respecto al jugador
+        guard_sounds.update(&enemies, &player, &maze, block_size, audio, &footsteps, &radio_chatter);
     
         // Dibuja solo los enemigos que están dentro del área visible del minimapa
-        draw_enemies_position(framebuffer, &enemies, player.get_pos(), block_size as usize, framebuffer.get_width(), framebuffer.get_height());
+        draw_enemies_position(framebuffer, &enemies, &occupancy, &minimap_transform);
     
         // Dibuja el campo de visión de cada enemigo
         for enemy in &enemies {
-            draw_enemy_fov(framebuffer, &enemy, 30, &maze, block_size, enemy_in_map, player.get_pos(), 2);
+            draw_enemy_fov(framebuffer, &enemy, 30, &maze, block_size, &occupancy, &minimap_transform);
         }
     
-        let (maze, player_pos) = render(framebuffer, file_path, 0.5);
-    
         // Dibuja la posición del jugador en el minimapa
-        draw_player_position(framebuffer, player.get_pos(), block_size as usize, map_chosen);
+        draw_player_position(framebuffer, player.get_pos(), &minimap_transform);
+
+        // HUD sobre la vista 3D; el mapa completo lo tapa
+        let seeing: Vec<bool> = enemies.iter().map(|enemy| enemy.can_see(player.get_pos(), &maze, block_size)).collect();
+        hud.update_detection(seeing.contains(&true), delta_time);
+        let detected = hud.detection() >= 1.0;
+        guard_clips = enemies.iter().zip(&seeing).map(|(enemy, &sees)| Clip::for_guard(enemy, sees, detected)).collect();
+        let objective = if exploration.known_exits(&maze).is_empty() { "Find a way out" } else { "Reach the exit" };
+        hud.draw(framebuffer, &font, &HudState {
+            objective,
+            elapsed: start_time.elapsed(),
+            items: &player.items,
+            stamina: player.stamina,
+            prompt: hud_prompt(&maze, &doors, &player, block_size),
+        });
+
+        // Mapa completo con la memoria de exploración (tecla M)
+        if window.is_key_pressed(Key::M, KeyRepeat::No) {
+            show_full_map = !show_full_map;
+        }
+        if show_full_map {
+            draw_full_map(framebuffer, &maze, &exploration, &player, block_size);
+        }
     
         frame_count += 1;
         let fps = calculate_fps(start_time, frame_count);
     
-        if window.is_key_down(Key::F) {
-            framebuffer.draw_text(width - 100, 10, &format!("FPS: {:.2}", fps), Color::new(0, 255, 0), 20.0);
+        if settings.show_fps || window.is_key_down(Key::F) {
+            draw_label(framebuffer, &font, Anchor::TopRight, 20.0, 90.0, &format!("FPS: {:.2}", fps), TextStyle::new(20.0, Color::new(0, 255, 0)).with_outline(Color::new(0, 0, 0)));
         }
     
-        og_pos = new_pos;
-    
-        window.update_with_buffer(&framebuffer.get_buffer(), width, height).unwrap();
+        audio.update();
+        present_frame(framebuffer, window);
         std::thread::sleep(Duration::from_millis(16));
     }
 
     if enemy_collision {
         framebuffer.clear();
-        win_screen(framebuffer, window, width, height);
+        win_screen(framebuffer, window, width, height, assets, audio, settings)
     } else {
         framebuffer.clear();
-        defeat_screen(framebuffer, window, width, height);
+        defeat_screen(framebuffer, window, width, height, assets, audio, settings)
     }  
 }
 
-fn win_screen(framebuffer: &mut Framebuffer, window: &mut Window, width: usize, height: usize) {
-    let win_page = "textures/Ganar.png";
+fn win_screen(framebuffer: &mut Framebuffer, window: &mut Window, width: usize, height: usize, assets: &mut Assets, audio: &mut AudioEngine, settings: &mut Settings) -> Result<(), AssetError> {
+    let win_page = assets.width_fitted_image("textures/Ganar.png", width);
+    let font = assets.font(settings.font.path())?;
     let mut restart_game = false;
 
-    let mut audio_shot = AudioPlayer::new("Audio/Shot.wav", 0.5);
-    let mut audio_scream = AudioPlayer::new("Audio/hey.mp3", 0.5);
-    let mut audio_music = AudioPlayer::new("Audio/Liberado.mp3", 0.5);
+    let audio_shot = assets.sound("Audio/Shot.wav");
+    let audio_scream = assets.sound("Audio/hey.mp3");
+    let audio_music = assets.sound("Audio/Liberado.mp3");
     let mut shot_count = 0;
 
     let mut show_victory_screen = false;
 
     // Reproduce el audio del grito y espera a que termine antes de continuar
-    audio_scream.play();
+    audio.stop_music(MUSIC_FADE);
+    audio.play(&audio_scream, Bus::Sfx, 0.5);
     std::thread::sleep(Duration::from_secs(2)); // Ajusta el tiempo según la duración del audio si es necesario
 
     while window.is_open() && !window.is_key_down(minifb::Key::Escape) {
@@ -307,7 +483,7 @@ fn win_screen(framebuffer: &mut Framebuffer, window: &mut Window, width: usize,
 
         if shot_count < 2 {
             // Reproducir el audio de disparo y esperar un segundo entre reproducciones
-            audio_shot.play();
+            audio.play(&audio_shot, Bus::Sfx, 0.5);
             shot_count += 1;
             std::thread::sleep(Duration::from_millis(1000));
         } else if !show_victory_screen {
@@ -317,11 +493,11 @@ fn win_screen(framebuffer: &mut Framebuffer, window: &mut Window, width: usize,
 
         if show_victory_screen {
             // Mostrar la imagen y el texto solo después del segundo disparo
-            framebuffer.draw_image(&win_page, width, height);
-            framebuffer.draw_text(width / 5 + 55, 5 * height / 6, "Press R to play again", Color::new(255, 255, 255), 60.0);
+            framebuffer.draw_image(&win_page);
+            draw_label(framebuffer, &font, Anchor::Bottom, 0.0, 75.0, "Press R to play again", TextStyle::new(60.0, Color::new(255, 255, 255)).with_outline(Color::new(0, 0, 0)));
             
             // Reproducir la música de fondo
-            audio_music.play();
+            audio.play_music("win", &audio_music, MUSIC_FADE);
         }
 
         // Comprobar si se ha presionado la tecla 'R' para continuar con el juego
@@ -330,63 +506,240 @@ fn win_screen(framebuffer: &mut Framebuffer, window: &mut Window, width: usize,
             break;
         }
 
-        window.update_with_buffer(&framebuffer.get_buffer(), width, height).unwrap();
+        audio.update();
+        present_frame(framebuffer, window);
         std::thread::sleep(Duration::from_millis(16));
     }
     
     if restart_game {
-        audio_music.stop();
-        game_start(width, height, framebuffer, window);
+        return game_start(width, height, framebuffer, window, assets, audio, settings);
     }
+
+    Ok(())
 }
 
 
 
-fn defeat_screen(framebuffer: &mut Framebuffer, window: &mut Window, width: usize, height: usize) {
-    let defeat_screen = "textures/Perdida.png";
+fn defeat_screen(framebuffer: &mut Framebuffer, window: &mut Window, width: usize, height: usize, assets: &mut Assets, audio: &mut AudioEngine, settings: &mut Settings) -> Result<(), AssetError> {
+    let defeat_screen = assets.width_fitted_image("textures/Perdida.png", width);
+    let font = assets.font(settings.font.path())?;
 
     let mut restart_game = false;
 
-    let mut audio_end = AudioPlayer::new("Audio/Atrapado.mp3", 0.5);
+    audio.play_music("defeat", &assets.sound("Audio/Atrapado.mp3"), MUSIC_FADE);
 
     while window.is_open() && !window.is_key_down(minifb::Key::Escape) {
         framebuffer.clear();
 
-        framebuffer.draw_image(&defeat_screen, width, height);
-        framebuffer.draw_text(width / 5 + 55, 5 * height / 6, "Press R to play again", Color::new(255, 255, 255), 60.0);
+        framebuffer.draw_image(&defeat_screen);
+        draw_label(framebuffer, &font, Anchor::Bottom, 0.0, 75.0, "Press R to play again", TextStyle::new(60.0, Color::new(255, 255, 255)).with_outline(Color::new(0, 0, 0)));
 
         if window.is_key_down(minifb::Key::R) {
             restart_game = true;
             break;
         }
 
-        window.update_with_buffer(&framebuffer.get_buffer(), width, height).unwrap();
+        audio.update();
+        present_frame(framebuffer, window);
         std::thread::sleep(Duration::from_millis(16));
     }
 
     if restart_game {
-        audio_end.stop();
-        game_start(width, height, framebuffer, window);
+        return game_start(width, height, framebuffer, window, assets, audio, settings);
     }
+
+    Ok(())
 }
 
-fn controls_screen(framebuffer: &mut Framebuffer, file_path: &str, width: usize, height: usize, window: &mut Window, map_chosen: usize) {
+fn controls_screen(framebuffer: &mut Framebuffer, file_path: &str, window: &mut Window, map_chosen: usize, assets: &mut Assets, audio: &mut AudioEngine, settings: &mut Settings) -> Result<(), AssetError> {
+    let (width, height) = (settings.width, settings.height);
+    loading_screen(framebuffer, window, width, height, assets)?;
 
-    let controls_page = "textures/Controls.png";
+    let controls_page = assets.width_fitted_image("textures/Controls.png", width);
+    let font = assets.font(settings.font.path())?;
     let start_time = Instant::now();
 
     while window.is_open() && !window.is_key_down(minifb::Key::Escape) && start_time.elapsed() < Duration::from_secs(5) {
-        framebuffer.draw_image(&controls_page, width, height);
+        framebuffer.draw_image(&controls_page);
 
         let elapsed_secs = start_time.elapsed().as_secs();
         let remaining_time = 5 - elapsed_secs;
 
         let countdown_text = format!("Game starts in {}", remaining_time);
-        framebuffer.draw_text(width/4 + 45, 5*height/6, &countdown_text, Color::new(255, 255, 255), 70.0);
+        draw_label(framebuffer, &font, Anchor::Bottom, 0.0, 65.0, &countdown_text, TextStyle::new(70.0, Color::new(255, 255, 255)).with_outline(Color::new(0, 0, 0)));
 
-        window.update_with_buffer(framebuffer.get_buffer(), width, height).unwrap();
+        audio.update();
+        present_frame(framebuffer, window);
         std::thread::sleep(Duration::from_millis(16));
     }
 
-    gameplay(framebuffer, file_path, width, height, window, map_chosen);
+    gameplay(framebuffer, file_path, window, map_chosen, assets, audio, settings)
+}
+
+// Entradas del menú de ajustes, en orden
+const SETTINGS_ENTRIES: usize = 11;
+const SETTINGS_BACK: usize = SETTINGS_ENTRIES - 1;
+
+fn settings_entry(settings: &Settings, entry: usize) -> String {
+    match entry {
+        0 => format!("Render resolution: {}x{}", settings.width, settings.height),
+        1 => format!("Scaling: {}", if settings.scaling == Scaling::Integer { "integer" } else { "fractional" }),
+        2 => format!("3D render scale: {:.0}%", settings.render_scale * 100.0),
+        3 => format!("Field of view: {:.0}", settings.fov),
+        4 => format!("Mouse sensitivity: {:.3}", settings.mouse_sensitivity),
+        5 => format!("Music volume: {:.0}%", settings.music_volume * 100.0),
+        6 => format!("Effects volume: {:.0}%", settings.sfx_volume * 100.0),
+        7 => format!("Interface volume: {:.0}%", settings.ui_volume * 100.0),
+        8 => format!("Show FPS: {}", if settings.show_fps { "on" } else { "off" }),
+        9 => format!("Font: {}", settings.font.name()),
+        _ => "Back".to_string(),
+    }
+}
+
+// Cambia la entrada `entry` un paso hacia arriba (1) o hacia abajo (-1)
+fn adjust_setting(settings: &mut Settings, entry: usize, step: i32) {
+    let step_f = step as f32;
+    match entry {
+        0 => {
+            let current = RESOLUTIONS.iter().position(|&res| res == (settings.width, settings.height)).unwrap_or(1);
+            let next = (current as i32 + step).rem_euclid(RESOLUTIONS.len() as i32) as usize;
+            settings.width = RESOLUTIONS[next].0;
+            settings.height = RESOLUTIONS[next].1;
+        }
+        1 => settings.scaling = match settings.scaling {
+            Scaling::Integer => Scaling::Fractional,
+            Scaling::Fractional => Scaling::Integer,
+        },
+        2 => {
+            let current = RENDER_SCALES.iter().position(|&scale| scale == settings.render_scale).unwrap_or(RENDER_SCALES.len() - 1);
+            let next = (current as i32 + step).clamp(0, RENDER_SCALES.len() as i32 - 1) as usize;
+            settings.render_scale = RENDER_SCALES[next];
+        }
+        3 => settings.fov = (settings.fov + 5.0 * step_f).clamp(MIN_FOV, MAX_FOV),
+        4 => settings.mouse_sensitivity = (settings.mouse_sensitivity + 0.001 * step_f).clamp(MIN_SENSITIVITY, MAX_SENSITIVITY),
+        5 => settings.music_volume = (settings.music_volume + 0.1 * step_f).clamp(0.0, 1.0),
+        6 => settings.sfx_volume = (settings.sfx_volume + 0.1 * step_f).clamp(0.0, 1.0),
+        7 => settings.ui_volume = (settings.ui_volume + 0.1 * step_f).clamp(0.0, 1.0),
+        8 => settings.show_fps = !settings.show_fps,
+        9 => settings.font = settings.font.toggle(),
+        _ => {}
+    }
+}
+
+// Menú de ajustes: flechas arriba/abajo para elegir, izquierda/derecha para
+// cambiar. Cada cambio se guarda en el archivo de configuración. Devuelve
+// `true` si cambió la resolución.
+fn settings_screen(framebuffer: &mut Framebuffer, window: &mut Window, assets: &mut Assets, audio: &mut AudioEngine, settings: &mut Settings) -> Result<bool, AssetError> {
+    let mut font = assets.font(settings.font.path())?;
+    let resolution = (settings.width, settings.height);
+    let mut selected = 0;
+
+    // El menú solo se vuelve a dibujar cuando cambia la selección o un ajuste
+    let mut menu = CachedLayer::new();
+
+    while window.is_open() {
+        if window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+            selected = (selected + SETTINGS_ENTRIES - 1) % SETTINGS_ENTRIES;
+            menu.invalidate();
+            play_ui_sound(audio, assets, UI_SELECT);
+        }
+        if window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+            selected = (selected + 1) % SETTINGS_ENTRIES;
+            menu.invalidate();
+            play_ui_sound(audio, assets, UI_SELECT);
+        }
+
+        let mut step = 0;
+        if window.is_key_pressed(Key::Left, KeyRepeat::Yes) {
+            step = -1;
+        }
+        if window.is_key_pressed(Key::Right, KeyRepeat::Yes) {
+            step = 1;
+        }
+        if step != 0 && selected != SETTINGS_BACK {
+            adjust_setting(settings, selected, step);
+            font = assets.font(settings.font.path())?;
+            menu.invalidate();
+            // Después de aplicar los volúmenes, para oír el nuevo volumen de la interfaz
+            settings.apply_audio(audio);
+            play_ui_sound(audio, assets, UI_SELECT);
+            framebuffer.set_scaling(settings.scaling);
+            if let Err(e) = settings.save() {
+                eprintln!("warning: could not save settings: {}", e);
+            }
+        }
+
+        if window.is_key_pressed(Key::Backspace, KeyRepeat::No)
+            || (selected == SETTINGS_BACK && window.is_key_pressed(Key::Enter, KeyRepeat::No))
+        {
+            play_ui_sound(audio, assets, UI_CONFIRM);
+            break;
+        }
+
+        framebuffer.clear();
+        let settings = &*settings;
+        let layer = menu.get(framebuffer.get_width(), framebuffer.get_height(), 0, |layer| {
+            draw_label(layer, &font, Anchor::Top, 0.0, 64.0, "Settings", TextStyle::new(60.0, Color::new(255, 255, 255)));
+
+            for entry in 0..SETTINGS_ENTRIES {
+                let color = if entry == selected { Color::new(5, 166, 114) } else { Color::new(255, 255, 255) };
+                draw_label(layer, &font, Anchor::TopLeft, 200.0, 160.0 + entry as f32 * 55.0, &settings_entry(settings, entry), TextStyle::new(40.0, color));
+            }
+
+            if (settings.width, settings.height) != resolution {
+                draw_label(layer, &font, Anchor::BottomLeft, 200.0, 90.0, "The new resolution is applied when leaving this menu", TextStyle::new(24.0, Color::new(200, 200, 200)));
+            }
+            draw_label(layer, &font, Anchor::BottomLeft, 200.0, 50.0, "Backspace: back", TextStyle::new(24.0, Color::new(200, 200, 200)));
+        });
+        framebuffer.composite(layer, 0, 0, 1.0);
+
+        audio.update();
+        present_frame(framebuffer, window);
+        std::thread::sleep(Duration::from_millis(16));
+    }
+
+    // Espera a que se suelte Enter para que la pantalla de título no empiece el juego
+    while window.is_open() && window.is_key_down(Key::Enter) {
+        audio.update();
+        window.update();
+        std::thread::sleep(Duration::from_millis(16));
+    }
+
+    Ok((settings.width, settings.height) != resolution)
+}
+
+// Vuelve a crear el framebuffer con la resolución interna de los ajustes; la
+// ventana conserva su tamaño y la imagen se escala a ella
+fn resize_framebuffer(framebuffer: &mut Framebuffer, settings: &Settings) {
+    *framebuffer = Framebuffer::new(settings.width, settings.height);
+    framebuffer.set_scaling(settings.scaling);
+}
+
+// Precarga los recursos del nivel mostrando una barra de progreso
+fn loading_screen(framebuffer: &mut Framebuffer, window: &mut Window, width: usize, height: usize, assets: &mut Assets) -> Result<(), AssetError> {
+    let bar_width = width / 2;
+    let bar_height = 20;
+    let bar_x = (width - bar_width) as isize / 2;
+    let bar_y = (height - bar_height) as isize / 2;
+
+    assets.preload(&LEVEL_ASSETS, |loaded, total| {
+        let filled = (bar_width * loaded / total.max(1)) as isize;
+        let outline: Vec<[isize; 2]> = vec![
+            [bar_x, bar_y],
+            [bar_x + bar_width as isize, bar_y],
+            [bar_x + bar_width as isize, bar_y + bar_height as isize],
+            [bar_x, bar_y + bar_height as isize],
+        ];
+        let progress: Vec<[isize; 2]> = vec![
+            [bar_x, bar_y],
+            [bar_x + filled, bar_y],
+            [bar_x + filled, bar_y + bar_height as isize],
+            [bar_x, bar_y + bar_height as isize],
+        ];
+
+        framebuffer.clear();
+        framebuffer.polygon(&outline, Color::new(255, 255, 255), Color::new(40, 40, 40));
+        framebuffer.fill_polygon(&progress, Color::new(5, 166, 114));
+        present_frame(framebuffer, window);
+    })
 }
\ No newline at end of file
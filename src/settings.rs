@@ -0,0 +1,234 @@
+use crate::audio_backend::AudioBackendKind;
+use crate::audio_engine::{AudioEngine, Bus};
+use crate::framebuffer::Scaling;
+use crate::text::UiFont;
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+// Carpeta y archivo de configuración dentro del directorio de configuración del usuario
+const CONFIG_DIR: &str = "trapped";
+const CONFIG_FILE: &str = "settings.cfg";
+
+// Resoluciones que se pueden elegir en el menú
+pub const RESOLUTIONS: [(usize, usize); 5] = [(800, 600), (1000, 800), (1280, 720), (1280, 1024), (1920, 1080)];
+
+// Escalas de render de la vista 3D que se pueden elegir en el menú
+pub const RENDER_SCALES: [f32; 3] = [0.5, 0.75, 1.0];
+
+pub const MIN_FOV: f32 = 45.0;
+pub const MAX_FOV: f32 = 100.0;
+pub const MIN_SENSITIVITY: f32 = 0.002;
+pub const MAX_SENSITIVITY: f32 = 0.03;
+
+// Opciones del jugador. Se guardan como `clave=valor`, una por línea.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub width: usize, // Resolución interna de dibujo
+    pub height: usize,
+    pub scaling: Scaling,
+    pub render_scale: f32, // Fracción de la resolución interna usada por la vista 3D
+    pub fov: f32, // Grados
+    pub mouse_sensitivity: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub ui_volume: f32,
+    pub audio_backend: AudioBackendKind, // `TRAPPED_AUDIO` tiene prioridad
+    pub show_fps: bool,
+    pub font: UiFont,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            width: 1000,
+            height: 800,
+            scaling: Scaling::Integer,
+            render_scale: 1.0,
+            fov: 60.0,
+            mouse_sensitivity: 0.01,
+            music_volume: 0.5,
+            sfx_volume: 1.0,
+            ui_volume: 1.0,
+            audio_backend: AudioBackendKind::Auto,
+            show_fps: false,
+            font: UiFont::Pixel,
+        }
+    }
+}
+
+impl Settings {
+    // Lee las opciones de `text`. Las claves desconocidas o con valores no
+    // válidos se ignoran y conservan su valor por defecto.
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Settings::default();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    eprintln!("warning: ignoring config line '{}'", line);
+                    continue;
+                }
+            };
+            if !settings.set(key, value) {
+                eprintln!("warning: ignoring config entry '{}={}'", key, value);
+            }
+        }
+
+        settings.clamp();
+        settings
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "width" => value.parse().map(|v| self.width = v).is_ok(),
+            "height" => value.parse().map(|v| self.height = v).is_ok(),
+            "scaling" => match value {
+                "integer" => { self.scaling = Scaling::Integer; true }
+                "fractional" => { self.scaling = Scaling::Fractional; true }
+                _ => false,
+            },
+            "render_scale" => value.parse().map(|v| self.render_scale = v).is_ok(),
+            "fov" => value.parse().map(|v| self.fov = v).is_ok(),
+            "mouse_sensitivity" => value.parse().map(|v| self.mouse_sensitivity = v).is_ok(),
+            "music_volume" => value.parse().map(|v| self.music_volume = v).is_ok(),
+            "sfx_volume" => value.parse().map(|v| self.sfx_volume = v).is_ok(),
+            "ui_volume" => value.parse().map(|v| self.ui_volume = v).is_ok(),
+            "audio_backend" => AudioBackendKind::parse(value).map(|v| self.audio_backend = v).is_some(),
+            "show_fps" => value.parse().map(|v| self.show_fps = v).is_ok(),
+            "font" => UiFont::parse(value).map(|v| self.font = v).is_some(),
+            _ => false,
+        }
+    }
+
+    fn clamp(&mut self) {
+        // Una ventana más grande que la mayor resolución del menú reservaría un
+        // framebuffer enorme al arrancar
+        let max_width = RESOLUTIONS.iter().map(|&(width, _)| width).max().unwrap();
+        let max_height = RESOLUTIONS.iter().map(|&(_, height)| height).max().unwrap();
+        self.width = self.width.clamp(320, max_width);
+        self.height = self.height.clamp(240, max_height);
+        self.render_scale = self.render_scale.clamp(RENDER_SCALES[0], 1.0);
+        self.fov = self.fov.clamp(MIN_FOV, MAX_FOV);
+        self.mouse_sensitivity = self.mouse_sensitivity.clamp(MIN_SENSITIVITY, MAX_SENSITIVITY);
+        self.music_volume = self.music_volume.clamp(0.0, 1.0);
+        self.sfx_volume = self.sfx_volume.clamp(0.0, 1.0);
+        self.ui_volume = self.ui_volume.clamp(0.0, 1.0);
+    }
+
+    pub fn fov_radians(&self) -> f32 {
+        self.fov.to_radians()
+    }
+
+    // `$XDG_CONFIG_HOME` o `~/.config` en Linux y macOS, `%APPDATA%` en Windows
+    pub fn config_path() -> Option<PathBuf> {
+        let base = if cfg!(windows) {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            std::env::var_os("XDG_CONFIG_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+        base.map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    // Carga la configuración del usuario; si no existe se usan los valores por defecto
+    pub fn load() -> Self {
+        let path = match Settings::config_path() {
+            Some(path) => path,
+            None => return Settings::default(),
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Settings::parse(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Settings::default(),
+            Err(e) => {
+                eprintln!("warning: could not read '{}': {}; using default settings", path.display(), e);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Settings::config_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no user config directory"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_string())
+    }
+
+    // Aplica los volúmenes a los buses del motor de audio
+    pub fn apply_audio(&self, audio: &mut AudioEngine) {
+        audio.set_bus_volume(Bus::Music, self.music_volume);
+        audio.set_bus_volume(Bus::Sfx, self.sfx_volume);
+        audio.set_bus_volume(Bus::Ui, self.ui_volume);
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# Trapped: Operation Liberty")?;
+        writeln!(f, "width={}", self.width)?;
+        writeln!(f, "height={}", self.height)?;
+        writeln!(f, "scaling={}", match self.scaling { Scaling::Integer => "integer", Scaling::Fractional => "fractional" })?;
+        writeln!(f, "render_scale={}", self.render_scale)?;
+        writeln!(f, "fov={}", self.fov)?;
+        writeln!(f, "mouse_sensitivity={}", self.mouse_sensitivity)?;
+        writeln!(f, "music_volume={}", self.music_volume)?;
+        writeln!(f, "sfx_volume={}", self.sfx_volume)?;
+        writeln!(f, "ui_volume={}", self.ui_volume)?;
+        writeln!(f, "audio_backend={}", self.audio_backend.name())?;
+        writeln!(f, "show_fps={}", self.show_fps)?;
+        writeln!(f, "font={}", self.font.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_the_config_format() {
+        let settings = Settings {
+            width: 1280,
+            height: 720,
+            scaling: Scaling::Fractional,
+            render_scale: 0.75,
+            fov: 75.0,
+            mouse_sensitivity: 0.015,
+            music_volume: 0.25,
+            sfx_volume: 0.8,
+            ui_volume: 0.6,
+            audio_backend: AudioBackendKind::Null,
+            show_fps: true,
+            font: UiFont::Meditative,
+        };
+
+        assert_eq!(Settings::parse(&settings.to_string()), settings);
+    }
+
+    #[test]
+    fn resolution_is_kept_within_the_menu_range() {
+        let settings = Settings::parse("width=100000\nheight=10\n");
+
+        assert_eq!((settings.width, settings.height), (1920, 240));
+    }
+
+    #[test]
+    fn invalid_entries_keep_their_defaults() {
+        let settings = Settings::parse("fov = wide\nmusic_volume=3\nunknown=1\nshow_fps=true\naudio_backend=alsa\n");
+
+        assert_eq!(settings.fov, Settings::default().fov);
+        assert_eq!(settings.audio_backend, AudioBackendKind::Auto);
+        assert_eq!(settings.music_volume, 1.0);
+        assert!(settings.show_fps);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::assets::Assets;
+use crate::color::Color;
+use crate::enemy::Enemy;
+use crate::texture::Texture;
+
+use nalgebra_glm::Vec2;
+use std::f32::consts::PI;
+use std::rc::Rc;
+
+// Hoja de sprites de los guardias. Cada fila es una dirección de un clip y
+// cada columna un frame: primero las 8 direcciones de `Idle`, luego las de
+// `Walk`, `Alert` y `Aim` (ver `view_direction`). Todas las celdas miden lo mismo:
+// el ancho de la hoja entre `MAX_FRAMES` y el alto entre 8 * 4 filas.
+pub const GUARD_SHEET: &str = "textures/guard_sheet.png";
+// Si un mod quita la hoja, se usa este sprite para todas las direcciones y clips
+pub const GUARD_FALLBACK: &str = "textures/Police.png";
+
+const DIRECTIONS: usize = 8;
+const MAX_FRAMES: usize = 4;
+
+// Animaciones de un guardia
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Clip {
+    Idle,
+    Walk,
+    Alert, // Ha visto al jugador
+    Aim,   // El jugador ha sido detectado
+}
+
+impl Clip {
+    const ALL: [Clip; 4] = [Clip::Idle, Clip::Walk, Clip::Alert, Clip::Aim];
+
+    // Frames y frames por segundo
+    fn timing(self) -> (usize, f32) {
+        match self {
+            Clip::Idle => (2, 1.5),
+            Clip::Walk => (4, 8.0),
+            Clip::Alert => (2, 4.0),
+            Clip::Aim => (2, 2.0),
+        }
+    }
+
+    fn index(self) -> usize {
+        Clip::ALL.iter().position(|&clip| clip == self).unwrap_or(0)
+    }
+
+    // Clip según lo que hace el guardia: `sees_player` si el jugador está en su
+    // cono de visión y `detected` si el indicador de detección está lleno
+    pub fn for_guard(enemy: &Enemy, sees_player: bool, detected: bool) -> Self {
+        match (sees_player, detected) {
+            (true, true) => Clip::Aim,
+            (true, false) => Clip::Alert,
+            _ if enemy.is_moving() => Clip::Walk,
+            _ => Clip::Idle,
+        }
+    }
+}
+
+// Dirección en la que se ve al guardia: 0 de frente, 4 de espaldas y el resto
+// cada 45 grados en el sentido en que crece el ángulo del mundo
+pub fn view_direction(heading: f32, guard: Vec2, viewer: Vec2) -> usize {
+    let to_viewer = (viewer.y - guard.y).atan2(viewer.x - guard.x);
+    let relative = (to_viewer - heading).rem_euclid(2.0 * PI);
+    ((relative / (PI / 4.0)).round() as usize) % DIRECTIONS
+}
+
+// Rectángulo de una textura que se dibuja como sprite
+#[derive(Clone, Copy)]
+pub struct SpriteFrame<'a> {
+    texture: &'a Texture,
+    x: usize,
+    y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl<'a> SpriteFrame<'a> {
+    pub fn whole(texture: &'a Texture) -> Self {
+        SpriteFrame { texture, x: 0, y: 0, width: texture.width, height: texture.height }
+    }
+
+    pub fn get_color(&self, x: usize, y: usize) -> Color {
+        if x < self.width && y < self.height {
+            self.texture.get_color(self.x + x, self.y + y)
+        } else {
+            Color::transparent()
+        }
+    }
+}
+
+// Sprite colocado en el mundo (guardias y llaves), listo para dibujar en la
+// vista 3D
+pub struct PlacedSprite<'a> {
+    pub pos: Vec2,
+    pub frame: SpriteFrame<'a>,
+}
+
+pub struct SpriteSheet {
+    texture: Rc<Texture>,
+    // `false` para el sprite único de respaldo
+    animated: bool,
+}
+
+impl SpriteSheet {
+    pub fn new(texture: Rc<Texture>) -> Self {
+        SpriteSheet { texture, animated: true }
+    }
+
+    pub fn single(texture: Rc<Texture>) -> Self {
+        SpriteSheet { texture, animated: false }
+    }
+
+    // La hoja de los guardias, o el sprite fijo si no está en los recursos
+    pub fn guards(assets: &mut Assets) -> Self {
+        if assets.exists(GUARD_SHEET) {
+            SpriteSheet::new(assets.texture(GUARD_SHEET))
+        } else {
+            SpriteSheet::single(assets.texture(GUARD_FALLBACK))
+        }
+    }
+
+    // Frame de `clip` visto desde `direction` a los `time` segundos
+    pub fn frame(&self, clip: Clip, direction: usize, time: f32) -> SpriteFrame<'_> {
+        if !self.animated {
+            return SpriteFrame::whole(&self.texture);
+        }
+
+        let width = self.texture.width / MAX_FRAMES;
+        let height = self.texture.height / (DIRECTIONS * Clip::ALL.len());
+        let (frames, fps) = clip.timing();
+        let frame = (time.max(0.0) * fps) as usize % frames;
+        let row = clip.index() * DIRECTIONS + direction % DIRECTIONS;
+
+        SpriteFrame { texture: &self.texture, x: frame * width, y: row * height, width, height }
+    }
+
+    // Sprites de `enemies` vistos desde `viewer`. Cada guardia lleva un
+    // desfase para que no caminen todos al compás.
+    pub fn guard_sprites(&self, enemies: &[Enemy], clips: &[Clip], viewer: Vec2, time: f32) -> Vec<PlacedSprite<'_>> {
+        enemies
+            .iter()
+            .enumerate()
+            .map(|(i, enemy)| {
+                let clip = clips.get(i).copied().unwrap_or(Clip::Idle);
+                let direction = view_direction(enemy.heading(), enemy.get_pos(), viewer);
+                PlacedSprite { pos: enemy.get_pos(), frame: self.frame(clip, direction, time + i as f32 * 0.37) }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hoja en la que cada celda de 2x2 tiene como rojo su columna y como
+    // verde su fila
+    fn labelled_sheet() -> SpriteSheet {
+        let (width, height) = (MAX_FRAMES * 2, DIRECTIONS * Clip::ALL.len() * 2);
+        let data = (0..width * height).map(|i| Color::new(((i % width) / 2) as i32, ((i / width) / 2) as i32, 0)).collect();
+        SpriteSheet::new(Rc::new(Texture::new(width, height, data)))
+    }
+
+    fn cell(frame: &SpriteFrame) -> (u8, u8) {
+        let hex = frame.get_color(1, 1).to_hex();
+        ((hex >> 16) as u8, (hex >> 8) as u8)
+    }
+
+    #[test]
+    fn directions_follow_the_viewer_around_the_guard() {
+        let guard = Vec2::new(0.0, 0.0);
+        // Guardia mirando hacia +x
+        assert_eq!(view_direction(0.0, guard, Vec2::new(10.0, 0.0)), 0);
+        assert_eq!(view_direction(0.0, guard, Vec2::new(0.0, 10.0)), 2);
+        assert_eq!(view_direction(0.0, guard, Vec2::new(-10.0, 0.0)), 4);
+        assert_eq!(view_direction(0.0, guard, Vec2::new(10.0, -10.0)), 7);
+        assert_eq!(view_direction(PI, guard, Vec2::new(-10.0, 0.5)), 0);
+    }
+
+    #[test]
+    fn clips_pick_their_rows_and_advance_with_the_clock() {
+        let sheet = labelled_sheet();
+
+        assert_eq!(cell(&sheet.frame(Clip::Idle, 0, 0.0)), (0, 0));
+        assert_eq!(cell(&sheet.frame(Clip::Walk, 3, 0.0)), (0, 8 + 3));
+        assert_eq!(cell(&sheet.frame(Clip::Aim, 7, 0.0)), (0, 24 + 7));
+
+        // 8 frames por segundo, 4 frames: vuelve al primero a los 0,5 s
+        let walk: Vec<u8> = [0.0, 0.13, 0.26, 0.38, 0.5].iter().map(|&t| cell(&sheet.frame(Clip::Walk, 0, t)).0).collect();
+        assert_eq!(walk, [0, 1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn clips_follow_the_guard_state() {
+        let walking = Enemy::new(Vec2::new(0.0, 0.0), 0.0, 55.0, 22.5, 100.0);
+        let standing = Enemy::new(Vec2::new(0.0, 0.0), 0.0, 0.0, 22.5, 100.0);
+
+        assert_eq!(Clip::for_guard(&walking, false, false), Clip::Walk);
+        assert_eq!(Clip::for_guard(&standing, false, true), Clip::Idle);
+        assert_eq!(Clip::for_guard(&walking, true, false), Clip::Alert);
+        assert_eq!(Clip::for_guard(&standing, true, true), Clip::Aim);
+    }
+
+    #[test]
+    fn a_single_sprite_is_used_for_every_frame() {
+        let texture = Rc::new(Texture::new(3, 5, vec![Color::new(1, 2, 3); 15]));
+        let sheet = SpriteSheet::single(texture);
+        let frame = sheet.frame(Clip::Walk, 5, 12.3);
+
+        assert_eq!((frame.width, frame.height), (3, 5));
+        assert_eq!(frame.get_color(2, 4).to_hex(), 0x0001_0203);
+        assert_eq!(frame.get_color(3, 0).alpha(), 0);
+    }
+
+    // Suma de los colores de un frame, para distinguir frames distintos
+    fn checksum(frame: &SpriteFrame) -> u64 {
+        (0..frame.height).flat_map(|y| (0..frame.width).map(move |x| (x, y))).map(|(x, y)| frame.get_color(x, y).to_hex() as u64).sum()
+    }
+
+    #[test]
+    fn the_embedded_guard_sheet_has_every_direction_and_clip() {
+        use crate::asset_pack::AssetPack;
+
+        let sheet = SpriteSheet::guards(&mut Assets::new(AssetPack::new(None)));
+        assert!(sheet.animated);
+        assert_eq!(sheet.texture.width % MAX_FRAMES, 0);
+        assert_eq!(sheet.texture.height % (DIRECTIONS * Clip::ALL.len()), 0);
+
+        // Cada dirección de cada clip es una imagen distinta
+        for clip in Clip::ALL {
+            let views: Vec<u64> = (0..DIRECTIONS).map(|direction| checksum(&sheet.frame(clip, direction, 0.0))).collect();
+            for (i, view) in views.iter().enumerate() {
+                assert!(views[i + 1..].iter().all(|other| other != view), "{:?}: direction {} is repeated", clip, i);
+            }
+        }
+
+        // Los clips se distinguen entre sí y la caminata cambia con el tiempo
+        let front: Vec<u64> = Clip::ALL.iter().map(|&clip| checksum(&sheet.frame(clip, 0, 0.0))).collect();
+        assert!(front.iter().enumerate().all(|(i, a)| front[i + 1..].iter().all(|b| a != b)));
+        assert_ne!(checksum(&sheet.frame(Clip::Walk, 2, 0.0)), checksum(&sheet.frame(Clip::Walk, 2, 0.25)));
+    }
+}
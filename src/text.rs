@@ -0,0 +1,278 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::glyph_cache::CachedFont;
+
+use rusttype::{Font, Scale};
+
+// Fuentes incluidas con el juego
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UiFont {
+    Pixel,      // mai10.ttf
+    Meditative, // Meditative.ttf
+}
+
+impl UiFont {
+    pub const fn path(self) -> &'static str {
+        match self {
+            UiFont::Pixel => "fonts/mai10.ttf",
+            UiFont::Meditative => "fonts/Meditative.ttf",
+        }
+    }
+
+    // Nombre usado en el archivo de configuración y en el menú
+    pub fn name(self) -> &'static str {
+        match self {
+            UiFont::Pixel => "mai10",
+            UiFont::Meditative => "meditative",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "mai10" => Some(UiFont::Pixel),
+            "meditative" => Some(UiFont::Meditative),
+            _ => None,
+        }
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            UiFont::Pixel => UiFont::Meditative,
+            UiFont::Meditative => UiFont::Pixel,
+        }
+    }
+}
+
+// Alineación horizontal de cada línea dentro de su caja
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// Lo que se dibuja detrás del texto para que se lea sobre cualquier fondo
+#[derive(Clone, Copy)]
+pub enum Effect {
+    Plain,
+    Shadow(Color),
+    Outline(Color),
+}
+
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    pub size: f32,
+    pub color: Color,
+    pub align: Align,
+    pub effect: Effect,
+}
+
+impl TextStyle {
+    pub fn new(size: f32, color: Color) -> Self {
+        TextStyle { size, color, align: Align::Left, effect: Effect::Plain }
+    }
+
+    pub fn aligned(self, align: Align) -> Self {
+        TextStyle { align, ..self }
+    }
+
+    pub fn with_shadow(self, color: Color) -> Self {
+        TextStyle { effect: Effect::Shadow(color), ..self }
+    }
+
+    pub fn with_outline(self, color: Color) -> Self {
+        TextStyle { effect: Effect::Outline(color), ..self }
+    }
+
+    pub fn with_size(self, size: f32) -> Self {
+        TextStyle { size, ..self }
+    }
+}
+
+// Distancia entre la parte de arriba de dos líneas seguidas
+pub fn line_height(font: &Font, size: f32) -> usize {
+    let v_metrics = font.v_metrics(Scale::uniform(size));
+    (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap).ceil() as usize
+}
+
+// Ancho de una línea sin saltos
+pub fn line_width(font: &Font, text: &str, size: f32) -> usize {
+    Framebuffer::text_size(font, text, size).0
+}
+
+// Parte `text` en líneas que caben en `max_width`. Respeta los saltos de
+// línea del texto; una palabra más ancha que la caja queda sola en su línea.
+pub fn wrap(font: &Font, text: &str, size: f32, max_width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if line.is_empty() {
+                line.push_str(word);
+                continue;
+            }
+            let candidate = format!("{} {}", line, word);
+            if line_width(font, &candidate, size) > max_width {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn lines_height(font: &Font, count: usize, size: f32) -> usize {
+    match count {
+        0 => 0,
+        _ => (count - 1) * line_height(font, size) + Framebuffer::text_size(font, "", size).1,
+    }
+}
+
+// Grosor del contorno y desplazamiento de la sombra según el tamaño
+fn effect_offset(size: f32) -> isize {
+    (size / 20.0).round().max(1.0) as isize
+}
+
+// Dibuja una línea alineada respecto a `x`: empieza en `x`, se centra en `x`
+// o termina en `x` según `style.align`
+pub fn draw_line(framebuffer: &mut Framebuffer, font: &CachedFont, x: isize, y: isize, text: &str, style: &TextStyle) {
+    let width = line_width(font, text, style.size) as isize;
+    let x = match style.align {
+        Align::Left => x,
+        Align::Center => x - width / 2,
+        Align::Right => x - width,
+    };
+
+    let offset = effect_offset(style.size);
+    match style.effect {
+        Effect::Plain => {}
+        Effect::Shadow(color) => font.draw(framebuffer, x + offset, y + offset, text, color, style.size),
+        Effect::Outline(color) => {
+            for dy in [-offset, 0, offset] {
+                for dx in [-offset, 0, offset] {
+                    if dx != 0 || dy != 0 {
+                        font.draw(framebuffer, x + dx, y + dy, text, color, style.size);
+                    }
+                }
+            }
+        }
+    }
+    font.draw(framebuffer, x, y, text, style.color, style.size);
+}
+
+// Dibuja `text` en la caja que empieza en (x, y) con ancho `width`, partido
+// en líneas y alineado dentro de ella. Devuelve el alto ocupado.
+pub fn draw_text_box(framebuffer: &mut Framebuffer, font: &CachedFont, x: isize, y: isize, width: usize, text: &str, style: &TextStyle) -> usize {
+    let lines = wrap(font, text, style.size, width);
+    let anchor_x = match style.align {
+        Align::Left => x,
+        Align::Center => x + width as isize / 2,
+        Align::Right => x + width as isize,
+    };
+
+    let step = line_height(font, style.size) as isize;
+    for (i, line) in lines.iter().enumerate() {
+        draw_line(framebuffer, font, anchor_x, y + i as isize * step, line, style);
+    }
+    lines_height(font, lines.len(), style.size)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rusttype::point;
+
+    fn load(font: UiFont) -> CachedFont {
+        CachedFont::new(Font::try_from_vec(std::fs::read(font.path()).unwrap()).unwrap())
+    }
+
+    // Ancho y alto que ocupa `text` partido a `max_width`
+    fn measure(font: &Font, text: &str, size: f32, max_width: usize) -> (usize, usize) {
+        let lines = wrap(font, text, size, max_width);
+        let width = lines.iter().map(|line| line_width(font, line, size)).max().unwrap_or(0);
+        (width, lines_height(font, lines.len(), size))
+    }
+
+    // Dibuja una línea de texto con (x, y) en la esquina superior izquierda,
+    // mezclando cada píxel según la parte que cubre el glifo. El juego dibuja
+    // el texto con `CachedFont`; esta versión directa sirve de referencia.
+    pub(crate) fn blend_text(framebuffer: &mut Framebuffer, font: &Font, x: isize, y: isize, text: &str, color: Color, scale: f32) {
+        let scale = Scale::uniform(scale);
+        let v_metrics = font.v_metrics(scale);
+        let start_point = point(x as f32, y as f32 + v_metrics.ascent);
+        let alpha = color.alpha() as f32;
+
+        for glyph in font.layout(text, scale, start_point) {
+            if let Some(bounding_box) = glyph.pixel_bounding_box() {
+                glyph.draw(|gx, gy, coverage| {
+                    let px = gx as isize + bounding_box.min.x as isize;
+                    let py = gy as isize + bounding_box.min.y as isize;
+                    let coverage = (coverage * alpha).round() as u8;
+                    if coverage > 0 {
+                        framebuffer.blend_point(px, py, color.with_alpha(coverage));
+                    }
+                });
+            }
+        }
+    }
+
+    // Columnas (mínima, máxima) con algún píxel pintado
+    fn painted_columns(framebuffer: &Framebuffer) -> (usize, usize) {
+        let width = framebuffer.get_width();
+        let columns: Vec<usize> = framebuffer
+            .get_buffer()
+            .iter()
+            .enumerate()
+            .filter(|(_, &pixel)| pixel != 0)
+            .map(|(i, _)| i % width)
+            .collect();
+        (*columns.iter().min().unwrap(), *columns.iter().max().unwrap())
+    }
+
+    #[test]
+    fn wrapping_keeps_every_line_inside_the_box() {
+        for choice in [UiFont::Pixel, UiFont::Meditative] {
+            let font = load(choice);
+            let text = "Reach the exit without being seen by the guards\nGood luck";
+            let lines = wrap(&font, text, 24.0, 200);
+
+            assert!(lines.len() > 2);
+            assert_eq!(lines.last().unwrap(), "Good luck");
+            assert_eq!(lines.join(" ").split_whitespace().collect::<Vec<_>>(), text.split_whitespace().collect::<Vec<_>>());
+            for line in &lines {
+                assert!(line_width(&font, line, 24.0) <= 200, "{:?} is too wide", line);
+            }
+
+            let (width, height) = measure(&font, text, 24.0, 200);
+            assert!(width <= 200);
+            assert!(height >= lines.len() * line_height(&font, 24.0) / 2);
+        }
+    }
+
+    #[test]
+    fn lines_are_aligned_inside_the_box() {
+        let font = load(UiFont::Pixel);
+        let white = Color::new(255, 255, 255);
+
+        let mut centered = Framebuffer::new(200, 40);
+        draw_text_box(&mut centered, &font, 0, 0, 200, "Level 1", &TextStyle::new(20.0, white).aligned(Align::Center));
+        let (left, right) = painted_columns(&centered);
+        assert!(left.abs_diff(199 - right) <= 3, "left margin {} right margin {}", left, 199 - right);
+
+        let mut right_aligned = Framebuffer::new(200, 40);
+        draw_text_box(&mut right_aligned, &font, 0, 0, 200, "Level 1", &TextStyle::new(20.0, white).aligned(Align::Right));
+        assert!(painted_columns(&right_aligned).1 >= 195);
+    }
+
+    #[test]
+    fn edges_are_blended_by_coverage() {
+        let font = load(UiFont::Meditative);
+        let mut framebuffer = Framebuffer::new(120, 40);
+        blend_text(&mut framebuffer, &font, 2, 2, "Trapped", Color::new(255, 255, 255), 24.0);
+
+        let partial = framebuffer.get_buffer().iter().filter(|&&pixel| pixel != 0 && pixel != 0x00FF_FFFF).count();
+        assert!(partial > 0);
+    }
+}
@@ -1,5 +1,4 @@
-use image::io::Reader as ImageReader;
-use image::GenericImageView;
+use image::{DynamicImage, GenericImageView};
 use crate::color::Color;
 
 pub struct Texture {
@@ -13,18 +12,14 @@ impl Texture {
         Texture{ width, height, data }
     }
 
-    pub fn from_file(path: &str) -> Self {
-        let img = ImageReader::open(path).unwrap().decode().unwrap();
+    pub fn from_image(img: &DynamicImage) -> Self {
         let (width, height) = img.dimensions();
         let mut data = Vec::new();
     
+        // Se conserva el canal alfa: los sprites lo usan para la transparencia
         for pixel in img.pixels() {
             let (r, g, b, a) = (pixel.2 .0[0], pixel.2 .0[1], pixel.2 .0[2], pixel.2 .0[3]);
-            if a > 0 { // Verificar si el píxel no es completamente transparente
-                data.push(Color::new(r as i32, g as i32, b as i32));
-            } else {
-                data.push(Color::new(0, 0, 0)); // Fondo transparente
-            }
+            data.push(Color::new_rgba(r as i32, g as i32, b as i32, a as i32));
         }
     
         Texture {
@@ -38,7 +33,7 @@ impl Texture {
         if x < self.width && y < self.height {
             self.data[y * self.width + x]
         } else {
-            Color::new(0,0,0)
+            Color::transparent()
         }
     }
 }